@@ -0,0 +1,160 @@
+use cpal::traits::{DeviceTrait, HostTrait};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tracing::info;
+use crate::audio::{DeviceMixer, DuckingControl, LimiterSettings, MixerBus, OutputRoute, OUTPUT_ROUTE, VIRTUAL_ROUTE, get_audio_manager};
+
+pub const NULL_DEVICE: &str = "null";
+
+// what the engine plays through: the routes a sound goes out on and the devices behind them
+pub trait AudioBackend: Send + Sync {
+    fn routes(&self) -> Vec<OutputRoute>;
+    fn route_limiter(&self, route: &str) -> Arc<LimiterSettings>;
+    fn ducking(&self) -> Arc<DuckingControl>;
+    // where local previews and unrouted sounds go
+    fn default_output(&self) -> Option<String>;
+    fn open_mixer(&self, device_name: &str, limiter_settings: Arc<LimiterSettings>) -> Result<DeviceMixer, String>;
+}
+
+// the sound cards, routed by the audio manager
+pub struct CpalBackend;
+
+impl AudioBackend for CpalBackend {
+    fn routes(&self) -> Vec<OutputRoute> {
+        get_audio_manager().get_routes()
+    }
+
+    fn route_limiter(&self, route: &str) -> Arc<LimiterSettings> {
+        get_audio_manager().route_limiter(route)
+    }
+
+    fn ducking(&self) -> Arc<DuckingControl> {
+        get_audio_manager().ducking()
+    }
+
+    fn default_output(&self) -> Option<String> {
+        cpal::default_host().default_output_device().and_then(|d| d.name().ok())
+    }
+
+    fn open_mixer(&self, device_name: &str, limiter_settings: Arc<LimiterSettings>) -> Result<DeviceMixer, String> {
+        let device = get_audio_manager()
+            .find_output_device(device_name)
+            .ok_or_else(|| format!("Device {} is not available", device_name))?;
+        DeviceMixer::open(&device, limiter_settings)
+    }
+}
+
+struct NullDevice {
+    bus: Option<MixerBus>,
+    recorded: Vec<f32>,
+}
+
+// stands in for the sound cards on machines without audio hardware. any device name opens, and
+// nothing plays until render pulls the mix, which is kept per device
+pub struct NullBackend {
+    channels: u16,
+    sample_rate: u32,
+    routes: Vec<OutputRoute>,
+    virtual_limiter: Arc<LimiterSettings>,
+    output_limiter: Arc<LimiterSettings>,
+    ducking: Arc<DuckingControl>,
+    devices: Mutex<HashMap<String, NullDevice>>,
+    rendered_frames: Mutex<usize>,
+}
+
+impl NullBackend {
+    pub fn new(channels: u16, sample_rate: u32, routes: Vec<OutputRoute>) -> Self {
+        Self {
+            channels: channels.max(1),
+            sample_rate,
+            routes,
+            virtual_limiter: Arc::new(LimiterSettings::default()),
+            output_limiter: Arc::new(LimiterSettings::default()),
+            ducking: Arc::new(DuckingControl::default()),
+            devices: Mutex::new(HashMap::new()),
+            rendered_frames: Mutex::new(0),
+        }
+    }
+
+    // the output route on the null device, like a fresh install with no virtual cable
+    pub fn default_routes() -> Vec<OutputRoute> {
+        vec![OutputRoute {
+            name: OUTPUT_ROUTE.to_string(),
+            device: Some(NULL_DEVICE.to_string()),
+            volume: 1.0,
+            muted: false,
+            channel_mode: Default::default(),
+        }]
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    // pulls the next frames of every open device's mix
+    pub fn render(&self, frames: usize) {
+        let samples = frames * self.channels as usize;
+        for device in self.devices.lock().unwrap().values_mut() {
+            match device.bus.as_mut() {
+                Some(bus) => device.recorded.extend((0..samples).map(|_| bus.next().unwrap_or(0.0))),
+                None => device.recorded.resize(device.recorded.len() + samples, 0.0),
+            }
+        }
+        *self.rendered_frames.lock().unwrap() += frames;
+    }
+
+    pub fn rendered_frames(&self) -> usize {
+        *self.rendered_frames.lock().unwrap()
+    }
+
+    // interleaved samples per device, every recording starts at the beginning of the render
+    pub fn take_recordings(&self) -> HashMap<String, Vec<f32>> {
+        self.devices
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .map(|(name, device)| (name.clone(), std::mem::take(&mut device.recorded)))
+            .collect()
+    }
+}
+
+impl AudioBackend for NullBackend {
+    fn routes(&self) -> Vec<OutputRoute> {
+        self.routes.clone()
+    }
+
+    fn route_limiter(&self, route: &str) -> Arc<LimiterSettings> {
+        if route == VIRTUAL_ROUTE {
+            self.virtual_limiter.clone()
+        } else {
+            self.output_limiter.clone()
+        }
+    }
+
+    fn ducking(&self) -> Arc<DuckingControl> {
+        self.ducking.clone()
+    }
+
+    fn default_output(&self) -> Option<String> {
+        Some(NULL_DEVICE.to_string())
+    }
+
+    fn open_mixer(&self, device_name: &str, limiter_settings: Arc<LimiterSettings>) -> Result<DeviceMixer, String> {
+        let (mixer, bus) = DeviceMixer::detached(self.channels, self.sample_rate, limiter_settings);
+        // a device opened partway through is padded so all recordings line up
+        let rendered = *self.rendered_frames.lock().unwrap() * self.channels as usize;
+        let mut devices = self.devices.lock().unwrap();
+        let device = devices
+            .entry(device_name.to_string())
+            .or_insert_with(|| NullDevice { bus: None, recorded: vec![0.0; rendered] });
+        device.bus = Some(bus);
+        info!("Opened null mixer for {} ({} ch @ {} Hz)", device_name, self.channels, self.sample_rate);
+        Ok(mixer)
+    }
+}
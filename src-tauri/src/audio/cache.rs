@@ -0,0 +1,261 @@
+use anyhow::Result;
+use once_cell::sync::OnceCell;
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    thread,
+};
+use tracing::info;
+use crate::audio::{DecodedAudio, SymphoniaAudioSource};
+
+pub const DEFAULT_CACHE_BUDGET_MB: u32 = 128;
+pub const DEFAULT_MAX_CLIP_SECONDS: f32 = 15.0;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SampleCacheConfig {
+    // 0 turns the cache off
+    pub budget_mb: u32,
+    // longer clips stream from disk unless the sound is marked for preload
+    pub max_clip_seconds: f32,
+}
+
+impl SampleCacheConfig {
+    fn budget_bytes(&self) -> usize {
+        self.budget_mb as usize * 1024 * 1024
+    }
+}
+
+impl Default for SampleCacheConfig {
+    fn default() -> Self {
+        Self {
+            budget_mb: DEFAULT_CACHE_BUDGET_MB,
+            max_clip_seconds: DEFAULT_MAX_CLIP_SECONDS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SampleCacheStats {
+    pub entries: usize,
+    pub used_bytes: usize,
+    pub budget_bytes: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+struct CacheEntry {
+    audio: Arc<DecodedAudio>,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<String, CacheEntry>,
+    // files being decoded in the background, so a burst of triggers only decodes once
+    pending: HashSet<String>,
+    // too long or undecodable, not retried on every trigger
+    rejected: HashSet<String>,
+    used_bytes: usize,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+impl CacheState {
+    // least recently used first, entries still held by a playing voice stay alive through their Arc
+    fn evict_to(&mut self, budget_bytes: usize) {
+        while self.used_bytes > budget_bytes {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(path, _)| path.clone())
+            else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&oldest) {
+                self.used_bytes -= entry.audio.size_bytes();
+                self.evictions += 1;
+            }
+        }
+    }
+}
+
+// decoded PCM of short sounds keyed by file path, so triggers skip opening and probing the file
+pub struct SampleCache {
+    config: Mutex<SampleCacheConfig>,
+    state: Mutex<CacheState>,
+}
+
+impl SampleCache {
+    fn new() -> Self {
+        Self {
+            config: Mutex::new(SampleCacheConfig::default()),
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    pub fn config(&self) -> SampleCacheConfig {
+        *self.config.lock().unwrap()
+    }
+
+    pub fn set_config(&self, config: SampleCacheConfig) {
+        let config = SampleCacheConfig {
+            budget_mb: config.budget_mb,
+            max_clip_seconds: config.max_clip_seconds.max(0.0),
+        };
+        *self.config.lock().unwrap() = config;
+        let mut state = self.state.lock().unwrap();
+        state.rejected.clear();
+        state.evict_to(config.budget_bytes());
+    }
+
+    pub fn get(&self, file_path: &str) -> Option<Arc<DecodedAudio>> {
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let clock = state.clock;
+        match state.entries.get_mut(file_path) {
+            Some(entry) => {
+                entry.last_used = clock;
+                let audio = entry.audio.clone();
+                state.hits += 1;
+                Some(audio)
+            }
+            None => {
+                state.misses += 1;
+                None
+            }
+        }
+    }
+
+    // decodes on the calling thread, preload lifts the clip length limit
+    pub fn load(&self, file_path: &str, preload: bool) -> Result<()> {
+        if self.state.lock().unwrap().entries.contains_key(file_path) {
+            return Ok(());
+        }
+        let config = self.config();
+        let audio = decode_file(file_path, (!preload).then_some(config.max_clip_seconds))?;
+        self.insert(file_path, Arc::new(audio))
+    }
+
+    // the whole file in memory whatever its length, kept in the cache when it fits. looping sounds
+    // play from this so wrapping around never goes back to the file
+    pub fn get_or_decode(&self, file_path: &str) -> Result<Arc<DecodedAudio>> {
+        if let Some(audio) = self.get(file_path) {
+            return Ok(audio);
+        }
+        let audio = Arc::new(decode_file(file_path, None)?);
+        if let Err(e) = self.insert(file_path, audio.clone()) {
+            info!("Not caching {}: {}", file_path, e);
+        }
+        Ok(audio)
+    }
+
+    fn insert(&self, file_path: &str, audio: Arc<DecodedAudio>) -> Result<()> {
+        let budget_bytes = self.config().budget_bytes();
+        let size = audio.size_bytes();
+        if size > budget_bytes {
+            return Err(anyhow::anyhow!("{} needs {} bytes, more than the whole cache", file_path, size));
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let last_used = state.clock;
+        if let Some(old) = state.entries.insert(file_path.to_string(), CacheEntry { audio, last_used }) {
+            state.used_bytes -= old.audio.size_bytes();
+        }
+        state.used_bytes += size;
+        state.evict_to(budget_bytes);
+        info!("Cached {} ({} KB, {} entries)", file_path, size / 1024, state.entries.len());
+        Ok(())
+    }
+
+    // used after a miss so the trigger that missed isn't held up by the decode
+    pub fn load_in_background(&'static self, file_path: String) {
+        if self.config().budget_mb == 0 {
+            return;
+        }
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.entries.contains_key(&file_path) || state.rejected.contains(&file_path) || !state.pending.insert(file_path.clone()) {
+                return;
+            }
+        }
+        thread::spawn(move || {
+            let result = self.load(&file_path, false);
+            let mut state = self.state.lock().unwrap();
+            if let Err(e) = result {
+                info!("Not caching {}: {}", file_path, e);
+                state.rejected.insert(file_path.clone());
+            }
+            state.pending.remove(&file_path);
+        });
+    }
+
+    // decodes a batch one after another on a single thread, (file path, preload) pairs
+    pub fn warm(&'static self, files: Vec<(String, bool)>) {
+        if self.config().budget_mb == 0 || files.is_empty() {
+            return;
+        }
+        thread::spawn(move || {
+            let count = files.len();
+            for (file_path, preload) in files {
+                if let Err(e) = self.load(&file_path, preload) {
+                    info!("Not caching {}: {}", file_path, e);
+                }
+            }
+            info!("Sample cache warm-up finished ({} sounds)", count);
+        });
+    }
+
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.entries.clear();
+        state.rejected.clear();
+        state.used_bytes = 0;
+    }
+
+    pub fn stats(&self) -> SampleCacheStats {
+        let budget_bytes = self.config().budget_bytes();
+        let state = self.state.lock().unwrap();
+        SampleCacheStats {
+            entries: state.entries.len(),
+            used_bytes: state.used_bytes,
+            budget_bytes,
+            hits: state.hits,
+            misses: state.misses,
+            evictions: state.evictions,
+        }
+    }
+}
+
+fn decode_file(file_path: &str, max_clip_seconds: Option<f32>) -> Result<DecodedAudio> {
+    let source = SymphoniaAudioSource::new(file_path, 0.0)?;
+    let channels = source.channels();
+    let sample_rate = source.sample_rate();
+    if let (Some(duration), Some(max)) = (source.duration(), max_clip_seconds) {
+        if duration > max {
+            return Err(anyhow::anyhow!("{:.1}s is longer than the {:.1}s limit", duration, max));
+        }
+    }
+    // files without a frame count are checked as they decode
+    let max_samples = max_clip_seconds.map(|max| (max as f64 * sample_rate as f64) as usize * channels as usize);
+    let mut samples = Vec::new();
+    for sample in source {
+        samples.push(sample);
+        if max_samples.is_some_and(|max| samples.len() > max) {
+            return Err(anyhow::anyhow!("longer than the {:.1}s limit", max_clip_seconds.unwrap_or_default()));
+        }
+    }
+    Ok(DecodedAudio { samples, channels, sample_rate })
+}
+
+static SAMPLE_CACHE: OnceCell<SampleCache> = OnceCell::new();
+
+pub fn get_sample_cache() -> &'static SampleCache {
+    SAMPLE_CACHE.get_or_init(SampleCache::new)
+}
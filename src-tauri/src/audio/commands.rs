@@ -1,5 +1,150 @@
-use crate::audio::{AudioDevice, get_audio_manager, get_audio_engine, AudioCommand};
+use crate::audio::{AudioDevice, AudioManager, AutoGainConfig, CompressorConfig, DuckingConfig, LimiterConfig, MicProcessingConfig, MicState, MissingDevice, NoiseGateConfig, OutputRoute, PlayingSoundInfo, SampleCacheStats, get_sample_cache, PlayOptions, INPUT_BUS, OUTPUT_ROUTE, VIRTUAL_ROUTE, emit_event, get_audio_manager, get_audio_engine, AudioCommand};
+use crate::database;
 use cpal::traits::{HostTrait, DeviceTrait};
+use serde::Serialize;
+use tracing::warn;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelectedDevices {
+    pub virtual_device: Option<String>,
+    pub output_device: Option<String>,
+    pub input_device: Option<String>,
+}
+
+// the routing table carries the virtual and output devices along with their volumes
+pub fn save_output_routes() -> Result<(), String> {
+    let routes = get_audio_manager().get_routes();
+    let value = serde_json::to_string(&routes).map_err(|e| e.to_string())?;
+    database::save_setting("output_routes", &value).map_err(|e| e.to_string())
+}
+
+pub fn save_input_settings() -> Result<(), String> {
+    let manager = get_audio_manager();
+    if let Some(name) = manager.get_input_device().and_then(|d| d.name().ok()) {
+        database::save_setting("input_device", &name).map_err(|e| e.to_string())?;
+    }
+    database::save_setting("input_volume", &manager.get_input_volume().to_string()).map_err(|e| e.to_string())
+}
+
+pub fn save_mic_processing() -> Result<(), String> {
+    let config = get_audio_manager().mic_processing().config();
+    let value = serde_json::to_string(&config).map_err(|e| e.to_string())?;
+    database::save_setting("mic_processing", &value).map_err(|e| e.to_string())
+}
+
+pub fn save_ducking() -> Result<(), String> {
+    let config = get_audio_manager().ducking().config();
+    let value = serde_json::to_string(&config).map_err(|e| e.to_string())?;
+    database::save_setting("ducking", &value).map_err(|e| e.to_string())
+}
+
+pub fn save_limiters() -> Result<(), String> {
+    let manager = get_audio_manager();
+    let virtual_value = serde_json::to_string(&manager.virtual_limiter().config()).map_err(|e| e.to_string())?;
+    database::save_setting("virtual_limiter", &virtual_value).map_err(|e| e.to_string())?;
+    let output_value = serde_json::to_string(&manager.output_limiter().config()).map_err(|e| e.to_string())?;
+    database::save_setting("output_limiter", &output_value).map_err(|e| e.to_string())
+}
+
+fn report_missing_device(manager: &AudioManager, bus: &str, device: String, fallback: Option<String>) {
+    warn!("Saved {} device {} is missing, falling back to {:?}", bus, device, fallback);
+    let missing = MissingDevice { bus: bus.to_string(), device, fallback };
+    manager.record_missing_device(missing.clone());
+    emit_event("audio-device-missing", missing);
+}
+
+// built-in routes keep the device detected at startup, extra routes are left unassigned
+fn restore_route(manager: &AudioManager, route: OutputRoute) {
+    let missing = route.device.clone().filter(|device| manager.find_output_device(device).is_none());
+    let Some(device) = missing else {
+        if let Err(e) = manager.set_route(route) {
+            warn!("Could not restore route: {}", e);
+        }
+        return;
+    };
+    let fallback = manager.get_route(&route.name).and_then(|existing| existing.device);
+    let name = route.name.clone();
+    let _ = manager.set_route(OutputRoute { device: fallback.clone(), ..route });
+    report_missing_device(manager, &name, device, fallback);
+}
+
+// restores routes, the input device, bus volumes, limiters, mic processing and ducking from the last session
+pub fn load_audio_settings() {
+    let manager = get_audio_manager();
+    if let Some(value) = database::get_setting("output_routes").ok().flatten() {
+        match serde_json::from_str::<Vec<OutputRoute>>(&value) {
+            Ok(routes) => {
+                for route in routes {
+                    restore_route(manager, route);
+                }
+            }
+            Err(e) => warn!("Ignoring saved output routes: {}", e),
+        }
+    }
+    if let Some(device) = database::get_setting("input_device").ok().flatten() {
+        if manager.set_input_device(&device).is_err() {
+            let fallback = manager.get_input_device().and_then(|d| d.name().ok());
+            report_missing_device(manager, INPUT_BUS, device, fallback);
+        }
+    }
+    let input_volume = database::get_setting("input_volume")
+        .ok()
+        .flatten()
+        .and_then(|value| value.parse::<f32>().ok());
+    if let Some(volume) = input_volume {
+        let _ = manager.set_input_volume(volume);
+    }
+    if let Some(value) = database::get_setting("virtual_limiter").ok().flatten() {
+        match serde_json::from_str::<LimiterConfig>(&value) {
+            Ok(config) => manager.virtual_limiter().set_config(config),
+            Err(e) => warn!("Ignoring saved virtual limiter: {}", e),
+        }
+    }
+    if let Some(value) = database::get_setting("output_limiter").ok().flatten() {
+        match serde_json::from_str::<LimiterConfig>(&value) {
+            Ok(config) => manager.output_limiter().set_config(config),
+            Err(e) => warn!("Ignoring saved output limiter: {}", e),
+        }
+    }
+    if let Some(value) = database::get_setting("mic_processing").ok().flatten() {
+        match serde_json::from_str::<MicProcessingConfig>(&value) {
+            Ok(config) => manager.mic_processing().set_config(config),
+            Err(e) => warn!("Ignoring saved mic processing: {}", e),
+        }
+    }
+    if let Some(value) = database::get_setting("ducking").ok().flatten() {
+        match serde_json::from_str::<DuckingConfig>(&value) {
+            Ok(config) => manager.ducking().set_config(config),
+            Err(e) => warn!("Ignoring saved ducking settings: {}", e),
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn get_selected_devices() -> Result<SelectedDevices, String> {
+    let manager = get_audio_manager();
+    Ok(SelectedDevices {
+        virtual_device: manager.get_route(VIRTUAL_ROUTE).and_then(|route| route.device),
+        output_device: manager.get_route(OUTPUT_ROUTE).and_then(|route| route.device),
+        input_device: manager.get_input_device().and_then(|d| d.name().ok()),
+    })
+}
+
+#[tauri::command]
+pub async fn get_sample_cache_stats() -> Result<SampleCacheStats, String> {
+    Ok(get_sample_cache().stats())
+}
+
+#[tauri::command]
+pub async fn clear_sample_cache() -> Result<(), String> {
+    get_sample_cache().clear();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_missing_devices() -> Result<Vec<MissingDevice>, String> {
+    Ok(get_audio_manager().missing_devices())
+}
 
 #[tauri::command]
 pub async fn get_audio_devices() -> Result<Vec<AudioDevice>, String> {
@@ -53,7 +198,8 @@ pub async fn set_virtual_device(device_name: String) -> Result<(), String> {
     let manager = get_audio_manager();
     manager
         .set_virtual_device(&device_name)
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    save_output_routes()
 }
 
 #[tauri::command]
@@ -61,7 +207,8 @@ pub async fn set_output_device(device_name: String) -> Result<(), String> {
     let manager = get_audio_manager();
     manager
         .set_output_device(&device_name)
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    save_output_routes()
 }
 
 #[tauri::command]
@@ -69,7 +216,8 @@ pub async fn set_input_device(device_name: String) -> Result<(), String> {
     let manager = get_audio_manager();
     manager
         .set_input_device(&device_name)
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    save_input_settings()
 }
 
 #[tauri::command]
@@ -83,7 +231,8 @@ pub async fn set_virtual_volume(volume: f32) -> Result<(), String> {
     let manager = get_audio_manager();
     manager
         .set_virtual_volume(volume)
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    save_output_routes()
 }
 
 #[tauri::command]
@@ -97,7 +246,30 @@ pub async fn set_output_volume(volume: f32) -> Result<(), String> {
     let manager = get_audio_manager();
     manager
         .set_output_volume(volume)
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    save_output_routes()
+}
+
+#[tauri::command]
+pub async fn get_virtual_limiter() -> Result<LimiterConfig, String> {
+    Ok(get_audio_manager().virtual_limiter().config())
+}
+
+#[tauri::command]
+pub async fn set_virtual_limiter(enabled: bool, ceiling_db: f32) -> Result<(), String> {
+    get_audio_manager().virtual_limiter().set_config(LimiterConfig { enabled, ceiling_db });
+    save_limiters()
+}
+
+#[tauri::command]
+pub async fn get_output_limiter() -> Result<LimiterConfig, String> {
+    Ok(get_audio_manager().output_limiter().config())
+}
+
+#[tauri::command]
+pub async fn set_output_limiter(enabled: bool, ceiling_db: f32) -> Result<(), String> {
+    get_audio_manager().output_limiter().set_config(LimiterConfig { enabled, ceiling_db });
+    save_limiters()
 }
 
 #[tauri::command]
@@ -111,7 +283,50 @@ pub async fn set_input_volume(volume: f32) -> Result<(), String> {
     let manager = get_audio_manager();
     manager
         .set_input_volume(volume)
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    save_input_settings()
+}
+
+#[tauri::command]
+pub async fn get_mic_processing() -> Result<MicProcessingConfig, String> {
+    Ok(get_audio_manager().mic_processing().config())
+}
+
+#[tauri::command]
+pub async fn set_mic_noise_gate(noise_gate: NoiseGateConfig) -> Result<(), String> {
+    let settings = get_audio_manager().mic_processing();
+    settings.set_config(MicProcessingConfig { noise_gate, ..settings.config() });
+    save_mic_processing()
+}
+
+#[tauri::command]
+pub async fn set_mic_auto_gain(auto_gain: AutoGainConfig) -> Result<(), String> {
+    let settings = get_audio_manager().mic_processing();
+    settings.set_config(MicProcessingConfig { auto_gain, ..settings.config() });
+    save_mic_processing()
+}
+
+#[tauri::command]
+pub async fn set_mic_compressor(compressor: CompressorConfig) -> Result<(), String> {
+    let settings = get_audio_manager().mic_processing();
+    settings.set_config(MicProcessingConfig { compressor, ..settings.config() });
+    save_mic_processing()
+}
+
+#[tauri::command]
+pub async fn get_mic_state() -> Result<MicState, String> {
+    Ok(get_audio_manager().mic_state())
+}
+
+#[tauri::command]
+pub async fn get_ducking() -> Result<DuckingConfig, String> {
+    Ok(get_audio_manager().ducking().config())
+}
+
+#[tauri::command]
+pub async fn set_ducking(config: DuckingConfig) -> Result<(), String> {
+    get_audio_manager().ducking().set_config(config);
+    save_ducking()
 }
 
 #[tauri::command]
@@ -169,13 +384,11 @@ pub async fn get_audio_status() -> Result<String, String> {
 }
 
 #[tauri::command]
-pub async fn play_audio_file_command(file_path: String, sound_id: String, start_position: Option<f32>, sound_volume: f32, local_only: Option<bool>) -> Result<(), String> {
+pub async fn play_audio_file_command(file_path: String, sound_id: String, options: Option<PlayOptions>) -> Result<(), String> {
     get_audio_engine().send_command(AudioCommand::Play {
         file_path,
         sound_id,
-        start_position,
-        sound_volume,
-        local_only: local_only.unwrap_or(false),
+        options: options.unwrap_or_default(),
     });
     Ok(())
 }
@@ -186,12 +399,48 @@ pub async fn stop_sound_command(sound_id: String) -> Result<(), String> {
     Ok(())
 }
 
+#[tauri::command]
+pub async fn stop_voice_command(voice_id: String) -> Result<(), String> {
+    get_audio_engine().send_command(AudioCommand::StopVoice { voice_id });
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn stop_all_sounds_command() -> Result<(), String> {
     get_audio_engine().send_command(AudioCommand::StopAll);
     Ok(())
 }
 
+#[tauri::command]
+pub async fn pause_sound_command(sound_id: String) -> Result<(), String> {
+    get_audio_engine().send_command(AudioCommand::Pause { sound_id });
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn resume_sound_command(sound_id: String) -> Result<(), String> {
+    get_audio_engine().send_command(AudioCommand::Resume { sound_id });
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn pause_all_sounds_command() -> Result<(), String> {
+    get_audio_engine().send_command(AudioCommand::PauseAll);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn resume_all_sounds_command() -> Result<(), String> {
+    get_audio_engine().send_command(AudioCommand::ResumeAll);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn seek_sound_command(sound_id: String, position: f32) -> Result<(), String> {
+    get_audio_engine().send_command(AudioCommand::Seek { sound_id, position });
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn update_sound_volume_command(sound_id: String, sound_volume: f32) -> Result<(), String> {
     get_audio_engine().send_command(AudioCommand::UpdateSoundVolume { sound_id, sound_volume });
@@ -209,6 +458,27 @@ pub async fn get_playing_sounds_command() -> Result<Vec<String>, String> {
     Ok(get_audio_engine().get_playing_sounds())
 }
 
+#[tauri::command]
+pub async fn get_playing_sounds_info_command() -> Result<Vec<PlayingSoundInfo>, String> {
+    Ok(get_audio_engine().get_playing_sounds_info())
+}
+
+#[tauri::command]
+pub async fn get_paused_sounds_command() -> Result<Vec<String>, String> {
+    Ok(get_audio_engine().get_paused_sounds())
+}
+
+#[tauri::command]
+pub async fn get_progress_interval() -> Result<u32, String> {
+    Ok(get_audio_engine().get_progress_interval())
+}
+
+#[tauri::command]
+pub async fn set_progress_interval(interval_ms: u32) -> Result<(), String> {
+    get_audio_engine().set_progress_interval(interval_ms);
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn get_playback_position(sound_id: String) -> Result<Option<f32>, String> {
     Ok(get_audio_engine().get_playback_position(&sound_id))
@@ -219,9 +489,12 @@ pub async fn restart_sound_from_position(sound_id: String, file_path: String, po
     get_audio_engine().send_command(AudioCommand::Play {
         file_path,
         sound_id,
-        start_position: Some(position),
-        sound_volume,
-        local_only,
+        options: PlayOptions {
+            start_position: Some(position),
+            sound_volume,
+            local_only,
+            ..Default::default()
+        },
     });
     Ok(())
 } 
\ No newline at end of file
@@ -0,0 +1,201 @@
+use anyhow::Result;
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use crate::audio::{db_to_gain, SeekableSource};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DuckingConfig {
+    pub enabled: bool,
+    // how far ducked audio is turned down while a sound plays
+    pub amount_db: f32,
+    pub attack_ms: f32,
+    pub release_ms: f32,
+    pub duck_mic: bool,
+    // sounds in this category are ducked along with the mic instead of triggering it
+    pub music_category: Option<String>,
+}
+
+impl Default for DuckingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            amount_db: -12.0,
+            attack_ms: 50.0,
+            release_ms: 500.0,
+            duck_mic: true,
+            music_category: None,
+        }
+    }
+}
+
+impl DuckingConfig {
+    fn clamped(mut self) -> Self {
+        self.amount_db = self.amount_db.clamp(-60.0, 0.0);
+        self.attack_ms = self.attack_ms.clamp(1.0, 2000.0);
+        self.release_ms = self.release_ms.clamp(1.0, 5000.0);
+        self.music_category = self.music_category.filter(|category| !category.trim().is_empty());
+        self
+    }
+
+    pub fn is_music(&self, categories: &[String]) -> bool {
+        self.music_category.as_ref().is_some_and(|music| categories.contains(music))
+    }
+}
+
+// what's ducked: the engine flips active as triggering sounds come and go, the mic and music voices
+// follow it on their device threads
+#[derive(Debug, Default)]
+pub struct DuckingControl {
+    config: Mutex<DuckingConfig>,
+    version: AtomicU64,
+    active: AtomicBool,
+}
+
+impl DuckingControl {
+    pub fn config(&self) -> DuckingConfig {
+        self.config.lock().unwrap().clone()
+    }
+
+    pub fn set_config(&self, config: DuckingConfig) {
+        *self.config.lock().unwrap() = config.clamped();
+        self.version.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    pub fn set_active(&self, active: bool) {
+        self.active.store(active, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum DuckTarget {
+    Mic,
+    // a sound voice, ducked when it's in the music category
+    Sound(Vec<String>),
+}
+
+// smoothed ducking gain for one voice, stepped once per frame
+pub struct Ducker {
+    control: Arc<DuckingControl>,
+    target: DuckTarget,
+    sample_rate: f32,
+    version: Option<u64>,
+    applies: bool,
+    ducked_gain: f32,
+    attack_coeff: f32,
+    release_coeff: f32,
+    gain: f32,
+}
+
+impl Ducker {
+    pub fn new(control: Arc<DuckingControl>, target: DuckTarget, sample_rate: u32) -> Self {
+        let mut ducker = Self {
+            control,
+            target,
+            sample_rate: sample_rate.max(1) as f32,
+            version: None,
+            applies: false,
+            ducked_gain: 1.0,
+            attack_coeff: 1.0,
+            release_coeff: 1.0,
+            gain: 1.0,
+        };
+        ducker.refresh();
+        // a voice starting mid-duck comes in already ducked instead of sweeping down
+        if ducker.applies && ducker.control.is_active() {
+            ducker.gain = ducker.ducked_gain;
+        }
+        ducker
+    }
+
+    // picks up new settings without blocking the device thread, a busy lock is retried next frame
+    fn refresh(&mut self) {
+        let version = self.control.version.load(Ordering::SeqCst);
+        if self.version == Some(version) {
+            return;
+        }
+        let Ok(config) = self.control.config.try_lock() else {
+            return;
+        };
+        self.applies = config.enabled
+            && match &self.target {
+                DuckTarget::Mic => config.duck_mic,
+                DuckTarget::Sound(categories) => config.is_music(categories),
+            };
+        self.ducked_gain = db_to_gain(config.amount_db);
+        self.attack_coeff = 1.0 - (-1.0 / (config.attack_ms * self.sample_rate / 1000.0).max(1.0)).exp();
+        self.release_coeff = 1.0 - (-1.0 / (config.release_ms * self.sample_rate / 1000.0).max(1.0)).exp();
+        self.version = Some(version);
+    }
+
+    pub fn next_gain(&mut self) -> f32 {
+        self.refresh();
+        let target = if self.applies && self.control.is_active() { self.ducked_gain } else { 1.0 };
+        let coeff = if target < self.gain { self.attack_coeff } else { self.release_coeff };
+        self.gain += (target - self.gain) * coeff;
+        self.gain
+    }
+}
+
+// applies a ducker to a sound's source, a frame at a time so channels move together
+pub struct DuckedSource<S> {
+    inner: S,
+    ducker: Ducker,
+    gain: f32,
+    frame_pos: usize,
+}
+
+impl<S: SeekableSource> DuckedSource<S> {
+    pub fn new(inner: S, control: Arc<DuckingControl>, categories: Vec<String>) -> Self {
+        let ducker = Ducker::new(control, DuckTarget::Sound(categories), inner.sample_rate());
+        Self { inner, ducker, gain: 1.0, frame_pos: 0 }
+    }
+}
+
+impl<S: SeekableSource> Iterator for DuckedSource<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.frame_pos == 0 {
+            self.gain = self.ducker.next_gain();
+        }
+        self.frame_pos = (self.frame_pos + 1) % self.inner.channels().max(1) as usize;
+        self.inner.next().map(|sample| sample * self.gain)
+    }
+}
+
+impl<S: SeekableSource> Source for DuckedSource<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+impl<S: SeekableSource> SeekableSource for DuckedSource<S> {
+    fn seek(&mut self, position: f32) -> Result<()> {
+        self.frame_pos = 0;
+        self.inner.seek(position)
+    }
+}
@@ -0,0 +1,287 @@
+use serde::{Deserialize, Serialize};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+use crate::audio::db_to_gain;
+
+// the gate stays open this long after the level drops, so word endings aren't clipped
+const GATE_HOLD_MS: f32 = 80.0;
+// once open it closes this far below the threshold, so a level hovering there doesn't chatter
+const GATE_HYSTERESIS_DB: f32 = 4.0;
+// how far a closed gate turns the mic down
+const GATE_RANGE_DB: f32 = -60.0;
+// decay of the gate's level detector
+const GATE_DETECTOR_MS: f32 = 10.0;
+// auto gain follows the loudness over roughly this window
+const AUTO_GAIN_WINDOW_MS: f32 = 400.0;
+// anything quieter is treated as room noise and never turned up
+const AUTO_GAIN_NOISE_FLOOR_DB: f32 = -55.0;
+// auto gain backs off quickly when you get louder and creeps up when you get quieter
+const AUTO_GAIN_RISE_DB_PER_S: f32 = 3.0;
+const AUTO_GAIN_FALL_DB_PER_S: f32 = 12.0;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoiseGateConfig {
+    pub enabled: bool,
+    pub threshold_db: f32,
+    pub attack_ms: f32,
+    pub release_ms: f32,
+}
+
+impl Default for NoiseGateConfig {
+    fn default() -> Self {
+        Self { enabled: false, threshold_db: -50.0, attack_ms: 2.0, release_ms: 150.0 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompressorConfig {
+    pub enabled: bool,
+    pub threshold_db: f32,
+    pub ratio: f32,
+    pub attack_ms: f32,
+    pub release_ms: f32,
+    pub makeup_db: f32,
+}
+
+impl Default for CompressorConfig {
+    fn default() -> Self {
+        Self { enabled: false, threshold_db: -20.0, ratio: 4.0, attack_ms: 5.0, release_ms: 100.0, makeup_db: 0.0 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoGainConfig {
+    pub enabled: bool,
+    // loudness the mic is steered towards
+    pub target_db: f32,
+    // most it will turn a quiet mic up
+    pub max_gain_db: f32,
+}
+
+impl Default for AutoGainConfig {
+    fn default() -> Self {
+        Self { enabled: false, target_db: -20.0, max_gain_db: 20.0 }
+    }
+}
+
+// mic passthrough processing, applied in this order
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MicProcessingConfig {
+    pub noise_gate: NoiseGateConfig,
+    pub auto_gain: AutoGainConfig,
+    pub compressor: CompressorConfig,
+}
+
+impl MicProcessingConfig {
+    fn clamped(mut self) -> Self {
+        self.noise_gate.threshold_db = self.noise_gate.threshold_db.clamp(-90.0, 0.0);
+        self.noise_gate.attack_ms = self.noise_gate.attack_ms.clamp(0.1, 500.0);
+        self.noise_gate.release_ms = self.noise_gate.release_ms.clamp(1.0, 5000.0);
+        self.auto_gain.target_db = self.auto_gain.target_db.clamp(-40.0, -3.0);
+        self.auto_gain.max_gain_db = self.auto_gain.max_gain_db.clamp(0.0, 40.0);
+        self.compressor.threshold_db = self.compressor.threshold_db.clamp(-60.0, 0.0);
+        self.compressor.ratio = self.compressor.ratio.clamp(1.0, 20.0);
+        self.compressor.attack_ms = self.compressor.attack_ms.clamp(0.1, 500.0);
+        self.compressor.release_ms = self.compressor.release_ms.clamp(1.0, 5000.0);
+        self.compressor.makeup_db = self.compressor.makeup_db.clamp(0.0, 24.0);
+        self
+    }
+}
+
+// shared between the manager (commands write it) and the mic source (picks up changes between frames)
+#[derive(Debug)]
+pub struct MicProcessingSettings {
+    config: Mutex<MicProcessingConfig>,
+    version: AtomicU64,
+}
+
+impl MicProcessingSettings {
+    pub fn new(config: MicProcessingConfig) -> Self {
+        Self {
+            config: Mutex::new(config.clamped()),
+            version: AtomicU64::new(0),
+        }
+    }
+
+    pub fn config(&self) -> MicProcessingConfig {
+        *self.config.lock().unwrap()
+    }
+
+    pub fn set_config(&self, config: MicProcessingConfig) {
+        *self.config.lock().unwrap() = config.clamped();
+        self.version.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl Default for MicProcessingSettings {
+    fn default() -> Self {
+        Self::new(MicProcessingConfig::default())
+    }
+}
+
+fn gain_to_db(gain: f32) -> f32 {
+    20.0 * gain.max(1e-9).log10()
+}
+
+// one-pole smoothing coefficient that covers most of the distance in ms
+fn time_coeff(ms: f32, sample_rate: f32) -> f32 {
+    1.0 - (-1.0 / (ms * sample_rate / 1000.0).max(1.0)).exp()
+}
+
+struct Coefficients {
+    gate_attack: f32,
+    gate_release: f32,
+    gate_detector: f32,
+    gate_hold_frames: u32,
+    compressor_attack: f32,
+    compressor_release: f32,
+    auto_gain_window: f32,
+    auto_gain_rise: f32,
+    auto_gain_fall: f32,
+}
+
+impl Coefficients {
+    fn new(config: &MicProcessingConfig, sample_rate: f32) -> Self {
+        Self {
+            gate_attack: time_coeff(config.noise_gate.attack_ms, sample_rate),
+            gate_release: time_coeff(config.noise_gate.release_ms, sample_rate),
+            gate_detector: time_coeff(GATE_DETECTOR_MS, sample_rate),
+            gate_hold_frames: (GATE_HOLD_MS * sample_rate / 1000.0) as u32,
+            compressor_attack: time_coeff(config.compressor.attack_ms, sample_rate),
+            compressor_release: time_coeff(config.compressor.release_ms, sample_rate),
+            auto_gain_window: time_coeff(AUTO_GAIN_WINDOW_MS, sample_rate),
+            auto_gain_rise: AUTO_GAIN_RISE_DB_PER_S / sample_rate,
+            auto_gain_fall: AUTO_GAIN_FALL_DB_PER_S / sample_rate,
+        }
+    }
+}
+
+// gate, auto gain and compressor over interleaved frames. channels are linked so the stereo image holds
+pub struct MicProcessor {
+    settings: Arc<MicProcessingSettings>,
+    version: u64,
+    config: MicProcessingConfig,
+    sample_rate: f32,
+    coeffs: Coefficients,
+    gate_envelope: f32,
+    gate_open: bool,
+    gate_hold: u32,
+    gate_gain: f32,
+    auto_gain_power: f32,
+    auto_gain_db: f32,
+    compressor_envelope: f32,
+}
+
+impl MicProcessor {
+    pub fn new(settings: Arc<MicProcessingSettings>, sample_rate: u32) -> Self {
+        let config = settings.config();
+        let sample_rate = sample_rate.max(1) as f32;
+        Self {
+            version: settings.version.load(Ordering::SeqCst),
+            coeffs: Coefficients::new(&config, sample_rate),
+            settings,
+            config,
+            sample_rate,
+            gate_envelope: 0.0,
+            gate_open: false,
+            gate_hold: 0,
+            gate_gain: 1.0,
+            auto_gain_power: 0.0,
+            auto_gain_db: 0.0,
+            compressor_envelope: 0.0,
+        }
+    }
+
+    // picks up new settings without blocking the audio thread, a busy lock is retried next frame
+    fn refresh(&mut self) {
+        let version = self.settings.version.load(Ordering::SeqCst);
+        if version == self.version {
+            return;
+        }
+        let Ok(config) = self.settings.config.try_lock() else {
+            return;
+        };
+        self.config = *config;
+        self.version = version;
+        self.coeffs = Coefficients::new(&self.config, self.sample_rate);
+    }
+
+    pub fn process(&mut self, frame: &mut [f32]) {
+        self.refresh();
+        let config = self.config;
+        let peak = frame.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        let mut gain = 1.0;
+
+        if config.noise_gate.enabled {
+            gain *= self.gate(peak, &config.noise_gate);
+        } else {
+            self.gate_open = true;
+            self.gate_gain = 1.0;
+        }
+        if config.auto_gain.enabled {
+            gain *= self.auto_gain(frame, &config.auto_gain);
+        }
+        if config.compressor.enabled {
+            gain *= self.compressor(peak * gain, &config.compressor);
+        }
+
+        if gain != 1.0 {
+            frame.iter_mut().for_each(|sample| *sample *= gain);
+        }
+    }
+
+    fn gate(&mut self, peak: f32, config: &NoiseGateConfig) -> f32 {
+        if peak > self.gate_envelope {
+            self.gate_envelope = peak;
+        } else {
+            self.gate_envelope += (peak - self.gate_envelope) * self.coeffs.gate_detector;
+        }
+        let level_db = gain_to_db(self.gate_envelope);
+        if level_db > config.threshold_db {
+            self.gate_open = true;
+            self.gate_hold = self.coeffs.gate_hold_frames;
+        } else if self.gate_open && level_db < config.threshold_db - GATE_HYSTERESIS_DB {
+            if self.gate_hold > 0 {
+                self.gate_hold -= 1;
+            } else {
+                self.gate_open = false;
+            }
+        }
+
+        let target = if self.gate_open { 1.0 } else { db_to_gain(GATE_RANGE_DB) };
+        let coeff = if target > self.gate_gain { self.coeffs.gate_attack } else { self.coeffs.gate_release };
+        self.gate_gain += (target - self.gate_gain) * coeff;
+        self.gate_gain
+    }
+
+    fn auto_gain(&mut self, frame: &[f32], config: &AutoGainConfig) -> f32 {
+        let power = frame.iter().map(|sample| sample * sample).sum::<f32>() / frame.len().max(1) as f32;
+        self.auto_gain_power += (power - self.auto_gain_power) * self.coeffs.auto_gain_window;
+        let level_db = 10.0 * self.auto_gain_power.max(1e-12).log10();
+
+        // only speech moves it, a closed gate or room noise would just wind the gain up
+        if self.gate_open && level_db > AUTO_GAIN_NOISE_FLOOR_DB {
+            let wanted = (config.target_db - level_db).clamp(-config.max_gain_db, config.max_gain_db);
+            if wanted > self.auto_gain_db {
+                self.auto_gain_db = (self.auto_gain_db + self.coeffs.auto_gain_rise).min(wanted);
+            } else {
+                self.auto_gain_db = (self.auto_gain_db - self.coeffs.auto_gain_fall).max(wanted);
+            }
+        }
+        db_to_gain(self.auto_gain_db)
+    }
+
+    fn compressor(&mut self, peak: f32, config: &CompressorConfig) -> f32 {
+        let coeff = if peak > self.compressor_envelope { self.coeffs.compressor_attack } else { self.coeffs.compressor_release };
+        self.compressor_envelope += (peak - self.compressor_envelope) * coeff;
+        let over = gain_to_db(self.compressor_envelope) - config.threshold_db;
+        let reduction = if over > 0.0 { over * (1.0 - 1.0 / config.ratio) } else { 0.0 };
+        db_to_gain(config.makeup_db - reduction)
+    }
+}
@@ -0,0 +1,812 @@
+use anyhow::Result;
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+use std::{
+    f64::consts::PI,
+    mem::{discriminant, Discriminant},
+    sync::{Arc, Mutex, atomic::{AtomicU64, Ordering}},
+    time::Duration,
+};
+use crate::audio::{SeekableSource, loudness::Biquad};
+
+const MIN_SPEED: f32 = 0.25;
+const MAX_SPEED: f32 = 4.0;
+const MAX_ECHO_MS: f32 = 2000.0;
+const PITCH_WINDOW_MS: f32 = 50.0;
+// reverb and echo keep ringing after the file ends, until they fall below -80 dBFS or this runs out
+const MAX_TAIL_SECONDS: f32 = 5.0;
+const TAIL_SILENCE: f32 = 1e-4;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Effect {
+    // how fast the file is read, preserve_pitch shifts the result back to the original pitch
+    Speed {
+        rate: f32,
+        #[serde(default)]
+        preserve_pitch: bool,
+    },
+    PitchShift { semitones: f32 },
+    Reverb { room_size: f32, damping: f32, mix: f32 },
+    Echo { delay_ms: f32, feedback: f32, mix: f32 },
+    Equalizer { low_db: f32, mid_db: f32, high_db: f32 },
+    LowPass { cutoff_hz: f32 },
+    HighPass { cutoff_hz: f32 },
+    Bitcrush { bits: u32, downsample: u32 },
+    Distortion { drive: f32, mix: f32 },
+}
+
+impl Effect {
+    fn speed(&self) -> f32 {
+        match self {
+            Effect::Speed { rate, .. } => rate.clamp(MIN_SPEED, MAX_SPEED),
+            _ => 1.0,
+        }
+    }
+
+    fn has_tail(&self) -> bool {
+        matches!(self, Effect::Reverb { .. } | Effect::Echo { .. })
+    }
+
+    // whether a processor built for self takes other's parameters through update
+    fn same_processor(&self, other: &Effect) -> bool {
+        let pitch = |effect: &Effect| matches!(effect, Effect::Speed { .. } | Effect::PitchShift { .. });
+        (pitch(self) && pitch(other)) || discriminant(self) == discriminant(other)
+    }
+}
+
+fn chain_speed(chain: &[Effect]) -> f64 {
+    chain.iter().map(Effect::speed).product::<f32>() as f64
+}
+
+fn chain_tail_frames(chain: &[Effect], sample_rate: u32) -> u64 {
+    if chain.iter().any(Effect::has_tail) {
+        (MAX_TAIL_SECONDS * sample_rate as f32) as u64
+    } else {
+        0
+    }
+}
+
+// the chain a sound's voices run. edits are built into processors on the engine thread and handed to
+// each voice ready-made, so the device callback never allocates or frees to pick them up
+pub struct EffectsControl {
+    chain: Mutex<Vec<Effect>>,
+    slots: Mutex<Vec<Arc<EffectsSlot>>>,
+}
+
+impl EffectsControl {
+    pub fn new(chain: Vec<Effect>) -> Self {
+        Self {
+            chain: Mutex::new(chain),
+            slots: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn set_chain(&self, chain: Vec<Effect>) {
+        for slot in self.slots.lock().unwrap().iter() {
+            slot.prepare(&chain);
+        }
+        *self.chain.lock().unwrap() = chain;
+    }
+
+    fn attach(&self, channels: usize, sample_rate: u32) -> Arc<EffectsSlot> {
+        let chain = self.chain.lock().unwrap().clone();
+        let slot = Arc::new(EffectsSlot {
+            channels,
+            sample_rate,
+            version: AtomicU64::new(0),
+            state: Mutex::new(SlotState { running: chain, pending: None, spent: None }),
+        });
+        self.slots.lock().unwrap().push(slot.clone());
+        slot
+    }
+}
+
+// a chain built for one voice, with fresh processors only where the running ones can't take the new
+// parameters. the voice hands it back holding everything it let go of, which is freed on the engine thread
+struct PreparedChain {
+    effects: Vec<Effect>,
+    fresh: Vec<Option<Box<dyn FrameProcessor>>>,
+    // room for the new chain and the replaced processors, reserved up front
+    processors: Vec<Box<dyn FrameProcessor>>,
+    retired: Vec<Box<dyn FrameProcessor>>,
+    speed: f64,
+    tail_frames: u64,
+}
+
+struct SlotState {
+    // what the voice runs, the base a pending chain is built against
+    running: Vec<Effect>,
+    pending: Option<PreparedChain>,
+    spent: Option<PreparedChain>,
+}
+
+// one voice's end of its sound's effects
+struct EffectsSlot {
+    channels: usize,
+    sample_rate: u32,
+    version: AtomicU64,
+    state: Mutex<SlotState>,
+}
+
+impl EffectsSlot {
+    fn prepare(&self, chain: &[Effect]) {
+        let mut state = self.state.lock().unwrap();
+        // an edit the voice never picked up is rebuilt against what it actually runs
+        let stale = (state.pending.take(), state.spent.take());
+        let fresh = chain
+            .iter()
+            .enumerate()
+            .map(|(i, effect)| match state.running.get(i) {
+                Some(running) if running.same_processor(effect) => None,
+                _ => Some(build_processor(effect, self.channels, self.sample_rate)),
+            })
+            .collect();
+        state.pending = Some(PreparedChain {
+            effects: chain.to_vec(),
+            fresh,
+            processors: Vec::with_capacity(chain.len()),
+            retired: Vec::with_capacity(state.running.len()),
+            speed: chain_speed(chain),
+            tail_frames: chain_tail_frames(chain, self.sample_rate),
+        });
+        drop(state);
+        drop(stale);
+        self.version.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+trait FrameProcessor: Send {
+    fn process(&mut self, frame: &mut [f32]);
+    // takes new parameters in place when it can, keeping delay lines and filter state so live edits don't click
+    fn update(&mut self, effect: &Effect) -> bool;
+    fn reset(&mut self);
+}
+
+fn build_processor(effect: &Effect, channels: usize, sample_rate: u32) -> Box<dyn FrameProcessor> {
+    match effect {
+        Effect::Speed { .. } | Effect::PitchShift { .. } => Box::new(PitchShifter::new(effect, channels, sample_rate)),
+        Effect::Reverb { .. } => Box::new(Reverb::new(effect, channels, sample_rate)),
+        Effect::Echo { .. } => Box::new(Echo::new(effect, channels, sample_rate)),
+        Effect::Equalizer { .. } | Effect::LowPass { .. } | Effect::HighPass { .. } => {
+            Box::new(Filter::new(effect, channels, sample_rate))
+        }
+        Effect::Bitcrush { .. } => Box::new(Bitcrush::new(effect, channels)),
+        Effect::Distortion { .. } => Box::new(Distortion::new(effect)),
+    }
+}
+
+// runs a voice through its effect chain. speed is applied first whatever its place in the chain since it
+// changes how fast the file is read, everything else processes one output frame at a time in chain order
+pub struct EffectChainSource<S> {
+    inner: S,
+    slot: Arc<EffectsSlot>,
+    version: u64,
+    processors: Vec<Box<dyn FrameProcessor>>,
+    speed: f64,
+    tail_frames: u64,
+    channels: usize,
+    sample_rate: u32,
+    // varispeed read head, it sits frac of the way from prev to next
+    prev: Vec<f32>,
+    next: Vec<f32>,
+    frac: f64,
+    primed: bool,
+    inner_done: bool,
+    tail_left: u64,
+    quiet_frames: u64,
+    out: Vec<f32>,
+    out_pos: usize,
+}
+
+impl<S> EffectChainSource<S>
+where
+    S: SeekableSource,
+{
+    pub fn new(inner: S, control: Arc<EffectsControl>) -> Self {
+        let channels = inner.channels().max(1) as usize;
+        let sample_rate = inner.sample_rate();
+        let slot = control.attach(channels, sample_rate);
+        let state = slot.state.lock().unwrap();
+        let processors = state.running.iter().map(|effect| build_processor(effect, channels, sample_rate)).collect();
+        let speed = chain_speed(&state.running);
+        let tail_frames = chain_tail_frames(&state.running, sample_rate);
+        drop(state);
+        Self {
+            inner,
+            version: slot.version.load(Ordering::SeqCst),
+            slot,
+            processors,
+            speed,
+            tail_frames,
+            channels,
+            sample_rate,
+            prev: vec![0.0; channels],
+            next: vec![0.0; channels],
+            frac: 0.0,
+            primed: false,
+            inner_done: false,
+            tail_left: 0,
+            quiet_frames: 0,
+            out: vec![0.0; channels],
+            out_pos: channels,
+        }
+    }
+
+    // swaps in a chain the engine prepared, only moving boxes and vectors that were allocated for it. the
+    // engine only holds the lock while preparing, if it's busy right now the next frame picks it up
+    fn sync_chain(&mut self) {
+        let version = self.slot.version.load(Ordering::SeqCst);
+        if version == self.version {
+            return;
+        }
+        let Ok(mut state) = self.slot.state.try_lock() else {
+            return;
+        };
+        self.version = version;
+        let Some(mut prepared) = state.pending.take() else {
+            return;
+        };
+        {
+            let mut running = self.processors.drain(..);
+            for (effect, fresh) in prepared.effects.iter().zip(prepared.fresh.iter_mut()) {
+                let processor = match (fresh.take(), running.next()) {
+                    (Some(fresh), Some(old)) => {
+                        prepared.retired.push(old);
+                        fresh
+                    }
+                    (Some(fresh), None) => fresh,
+                    (None, Some(mut old)) => {
+                        old.update(effect);
+                        old
+                    }
+                    // prepare builds a processor wherever nothing was running
+                    (None, None) => continue,
+                };
+                prepared.processors.push(processor);
+            }
+            prepared.retired.extend(running);
+        }
+        std::mem::swap(&mut self.processors, &mut prepared.processors);
+        self.speed = prepared.speed;
+        self.tail_frames = prepared.tail_frames;
+        std::mem::swap(&mut state.running, &mut prepared.effects);
+        state.spent = Some(prepared);
+    }
+
+    fn read_frame(inner: &mut S, frame: &mut [f32]) -> bool {
+        for sample in frame.iter_mut() {
+            match inner.next() {
+                Some(value) => *sample = value,
+                None => return false,
+            }
+        }
+        true
+    }
+
+    fn render_frame(&mut self) -> bool {
+        self.sync_chain();
+
+        if !self.inner_done && !self.primed {
+            self.primed = true;
+            if !Self::read_frame(&mut self.inner, &mut self.prev) {
+                self.inner_done = true;
+            } else if !Self::read_frame(&mut self.inner, &mut self.next) {
+                self.next.copy_from_slice(&self.prev);
+            }
+        }
+
+        if self.inner_done {
+            // nothing left to read, ring out whatever the chain still holds
+            if self.tail_left == 0 || self.quiet_frames > self.sample_rate as u64 / 10 {
+                return false;
+            }
+            self.tail_left -= 1;
+            self.out.iter_mut().for_each(|sample| *sample = 0.0);
+        } else {
+            let frac = self.frac as f32;
+            for (out, (prev, next)) in self.out.iter_mut().zip(self.prev.iter().zip(&self.next)) {
+                *out = prev + (next - prev) * frac;
+            }
+            self.frac += self.speed;
+            while self.frac >= 1.0 {
+                self.frac -= 1.0;
+                std::mem::swap(&mut self.prev, &mut self.next);
+                if !Self::read_frame(&mut self.inner, &mut self.next) {
+                    self.inner_done = true;
+                    self.tail_left = self.tail_frames;
+                    self.quiet_frames = 0;
+                    break;
+                }
+            }
+        }
+
+        for processor in &mut self.processors {
+            processor.process(&mut self.out);
+        }
+
+        if self.inner_done {
+            let peak = self.out.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+            self.quiet_frames = if peak < TAIL_SILENCE { self.quiet_frames + 1 } else { 0 };
+        }
+        true
+    }
+}
+
+impl<S> Iterator for EffectChainSource<S>
+where
+    S: SeekableSource,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.out_pos >= self.channels {
+            if !self.render_frame() {
+                return None;
+            }
+            self.out_pos = 0;
+        }
+        let sample = self.out[self.out_pos];
+        self.out_pos += 1;
+        Some(sample)
+    }
+}
+
+impl<S> Source for EffectChainSource<S>
+where
+    S: SeekableSource,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        if self.processors.is_empty() && self.speed == 1.0 {
+            self.inner.total_duration()
+        } else {
+            None
+        }
+    }
+}
+
+impl<S> SeekableSource for EffectChainSource<S>
+where
+    S: SeekableSource,
+{
+    fn seek(&mut self, position: f32) -> Result<()> {
+        self.inner.seek(position)?;
+        self.primed = false;
+        self.inner_done = false;
+        self.frac = 0.0;
+        self.out_pos = self.channels;
+        for processor in &mut self.processors {
+            processor.reset();
+        }
+        Ok(())
+    }
+}
+
+// granular shifter, two read heads sweep a short delay line at the shifted rate and crossfade
+// so each one is silent when it jumps back
+struct PitchShifter {
+    ratio: f32,
+    buffers: Vec<Vec<f32>>,
+    write: usize,
+    window: f32,
+    phase: f32,
+}
+
+impl PitchShifter {
+    fn new(effect: &Effect, channels: usize, sample_rate: u32) -> Self {
+        let window = (PITCH_WINDOW_MS * sample_rate as f32 / 1000.0).max(2.0);
+        let mut shifter = Self {
+            ratio: 1.0,
+            buffers: vec![vec![0.0; window as usize + 2]; channels],
+            write: 0,
+            window,
+            phase: 0.0,
+        };
+        shifter.update(effect);
+        shifter
+    }
+
+    fn read(buffer: &[f32], position: f32) -> f32 {
+        let len = buffer.len();
+        let index = position.floor();
+        let frac = position - index;
+        let i0 = (index as isize).rem_euclid(len as isize) as usize;
+        let i1 = (i0 + 1) % len;
+        buffer[i0] * (1.0 - frac) + buffer[i1] * frac
+    }
+}
+
+impl FrameProcessor for PitchShifter {
+    fn process(&mut self, frame: &mut [f32]) {
+        let len = self.buffers.first().map(|b| b.len()).unwrap_or(1);
+        for (buffer, sample) in self.buffers.iter_mut().zip(frame.iter_mut()) {
+            buffer[self.write] = *sample;
+            if (self.ratio - 1.0).abs() < 1e-4 {
+                continue;
+            }
+            let mut shifted = 0.0;
+            for offset in [0.0, 0.5] {
+                let phase = (self.phase + offset) % 1.0;
+                let gain = (std::f32::consts::PI * phase).sin().powi(2);
+                shifted += gain * Self::read(buffer, self.write as f32 - phase * self.window);
+            }
+            *sample = shifted;
+        }
+        self.phase = (self.phase + (1.0 - self.ratio) / self.window).rem_euclid(1.0);
+        self.write = (self.write + 1) % len;
+    }
+
+    fn update(&mut self, effect: &Effect) -> bool {
+        self.ratio = match effect {
+            Effect::PitchShift { semitones } => 2f32.powf(semitones.clamp(-24.0, 24.0) / 12.0),
+            Effect::Speed { preserve_pitch: true, .. } => 1.0 / effect.speed(),
+            Effect::Speed { .. } => 1.0,
+            _ => return false,
+        };
+        true
+    }
+
+    fn reset(&mut self) {
+        self.buffers.iter_mut().for_each(|buffer| buffer.fill(0.0));
+        self.phase = 0.0;
+    }
+}
+
+struct Comb {
+    buffer: Vec<f32>,
+    pos: usize,
+    store: f32,
+}
+
+impl Comb {
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let output = self.buffer[self.pos];
+        self.store = output * (1.0 - damping) + self.store * damping;
+        self.buffer[self.pos] = input + self.store * feedback;
+        self.pos = (self.pos + 1) % self.buffer.len();
+        output
+    }
+}
+
+struct Allpass {
+    buffer: Vec<f32>,
+    pos: usize,
+}
+
+impl Allpass {
+    fn process(&mut self, input: f32) -> f32 {
+        let buffered = self.buffer[self.pos];
+        self.buffer[self.pos] = input + buffered * 0.5;
+        self.pos = (self.pos + 1) % self.buffer.len();
+        buffered - input
+    }
+}
+
+// a cut down freeverb, four damped combs into two allpasses per channel
+struct Reverb {
+    combs: Vec<Vec<Comb>>,
+    allpasses: Vec<Vec<Allpass>>,
+    feedback: f32,
+    damping: f32,
+    mix: f32,
+}
+
+impl Reverb {
+    const COMB_TUNING: [usize; 4] = [1116, 1277, 1422, 1557];
+    const ALLPASS_TUNING: [usize; 2] = [556, 341];
+    // later channels get slightly longer delays so the tail spreads across the stereo field
+    const STEREO_SPREAD: usize = 23;
+
+    fn new(effect: &Effect, channels: usize, sample_rate: u32) -> Self {
+        let scale = |tuning: usize, channel: usize| {
+            (((tuning + channel * Self::STEREO_SPREAD) as f32 * sample_rate as f32 / 44100.0) as usize).max(1)
+        };
+        let combs = (0..channels)
+            .map(|ch| {
+                Self::COMB_TUNING
+                    .iter()
+                    .map(|t| Comb { buffer: vec![0.0; scale(*t, ch)], pos: 0, store: 0.0 })
+                    .collect()
+            })
+            .collect();
+        let allpasses = (0..channels)
+            .map(|ch| {
+                Self::ALLPASS_TUNING
+                    .iter()
+                    .map(|t| Allpass { buffer: vec![0.0; scale(*t, ch)], pos: 0 })
+                    .collect()
+            })
+            .collect();
+        let mut reverb = Self { combs, allpasses, feedback: 0.0, damping: 0.0, mix: 0.0 };
+        reverb.update(effect);
+        reverb
+    }
+}
+
+impl FrameProcessor for Reverb {
+    fn process(&mut self, frame: &mut [f32]) {
+        for ((sample, combs), allpasses) in frame.iter_mut().zip(&mut self.combs).zip(&mut self.allpasses) {
+            let input = *sample * 0.05;
+            let mut wet: f32 = combs.iter_mut().map(|comb| comb.process(input, self.feedback, self.damping)).sum();
+            for allpass in allpasses.iter_mut() {
+                wet = allpass.process(wet);
+            }
+            *sample = *sample * (1.0 - self.mix) + wet * 3.0 * self.mix;
+        }
+    }
+
+    fn update(&mut self, effect: &Effect) -> bool {
+        let Effect::Reverb { room_size, damping, mix } = effect else { return false };
+        self.feedback = 0.7 + room_size.clamp(0.0, 1.0) * 0.28;
+        self.damping = damping.clamp(0.0, 1.0) * 0.4;
+        self.mix = mix.clamp(0.0, 1.0);
+        true
+    }
+
+    fn reset(&mut self) {
+        for comb in self.combs.iter_mut().flatten() {
+            comb.buffer.fill(0.0);
+            comb.store = 0.0;
+        }
+        for allpass in self.allpasses.iter_mut().flatten() {
+            allpass.buffer.fill(0.0);
+        }
+    }
+}
+
+struct Echo {
+    buffers: Vec<Vec<f32>>,
+    pos: usize,
+    sample_rate: u32,
+    delay_frames: usize,
+    feedback: f32,
+    mix: f32,
+}
+
+impl Echo {
+    // sized for the longest delay up front, so changing the delay while playing doesn't reallocate
+    fn new(effect: &Effect, channels: usize, sample_rate: u32) -> Self {
+        let len = (MAX_ECHO_MS * sample_rate as f32 / 1000.0) as usize + 1;
+        let mut echo = Self {
+            buffers: vec![vec![0.0; len]; channels],
+            pos: 0,
+            sample_rate,
+            delay_frames: 1,
+            feedback: 0.0,
+            mix: 0.0,
+        };
+        echo.update(effect);
+        echo
+    }
+}
+
+impl FrameProcessor for Echo {
+    fn process(&mut self, frame: &mut [f32]) {
+        let len = self.buffers.first().map(|b| b.len()).unwrap_or(1);
+        let read = (self.pos + len - self.delay_frames) % len;
+        for (buffer, sample) in self.buffers.iter_mut().zip(frame.iter_mut()) {
+            let delayed = buffer[read];
+            buffer[self.pos] = *sample + delayed * self.feedback;
+            *sample += delayed * self.mix;
+        }
+        self.pos = (self.pos + 1) % len;
+    }
+
+    fn update(&mut self, effect: &Effect) -> bool {
+        let Effect::Echo { delay_ms, feedback, mix } = effect else { return false };
+        let len = self.buffers.first().map(|b| b.len()).unwrap_or(1);
+        self.delay_frames = ((delay_ms.clamp(1.0, MAX_ECHO_MS) * self.sample_rate as f32 / 1000.0) as usize).clamp(1, len - 1);
+        self.feedback = feedback.clamp(0.0, 0.95);
+        self.mix = mix.clamp(0.0, 1.0);
+        true
+    }
+
+    fn reset(&mut self) {
+        self.buffers.iter_mut().for_each(|buffer| buffer.fill(0.0));
+    }
+}
+
+// the EQ, low-pass and high-pass, as biquad stages from the audio EQ cookbook
+struct Filter {
+    kind: Discriminant<Effect>,
+    sample_rate: u32,
+    stages: Vec<Vec<Biquad>>,
+}
+
+impl Filter {
+    const LOW_SHELF_HZ: f64 = 250.0;
+    const MID_HZ: f64 = 1000.0;
+    const HIGH_SHELF_HZ: f64 = 4000.0;
+
+    fn new(effect: &Effect, channels: usize, sample_rate: u32) -> Self {
+        let stages = (0..channels).map(|_| Self::design(effect, sample_rate)).collect();
+        Self { kind: discriminant(effect), sample_rate, stages }
+    }
+
+    fn design(effect: &Effect, sample_rate: u32) -> Vec<Biquad> {
+        let sample_rate = sample_rate.max(1) as f64;
+        let nyquist = sample_rate / 2.0 * 0.95;
+        match effect {
+            Effect::LowPass { cutoff_hz } => vec![pass_filter(false, (*cutoff_hz as f64).clamp(20.0, nyquist), sample_rate)],
+            Effect::HighPass { cutoff_hz } => vec![pass_filter(true, (*cutoff_hz as f64).clamp(20.0, nyquist), sample_rate)],
+            Effect::Equalizer { low_db, mid_db, high_db } => vec![
+                shelf(false, Self::LOW_SHELF_HZ, *low_db as f64, sample_rate),
+                peaking(Self::MID_HZ, *mid_db as f64, 0.7, sample_rate),
+                shelf(true, Self::HIGH_SHELF_HZ.min(nyquist), *high_db as f64, sample_rate),
+            ],
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl FrameProcessor for Filter {
+    fn process(&mut self, frame: &mut [f32]) {
+        for (sample, stages) in frame.iter_mut().zip(&mut self.stages) {
+            let mut value = *sample as f64;
+            for stage in stages.iter_mut() {
+                value = stage.process(value);
+            }
+            *sample = value as f32;
+        }
+    }
+
+    fn update(&mut self, effect: &Effect) -> bool {
+        if discriminant(effect) != self.kind {
+            return false;
+        }
+        let design = Self::design(effect, self.sample_rate);
+        for stages in &mut self.stages {
+            for (stage, new) in stages.iter_mut().zip(&design) {
+                stage.set_coefficients(new);
+            }
+        }
+        true
+    }
+
+    fn reset(&mut self) {
+        self.stages.iter_mut().flatten().for_each(Biquad::reset);
+    }
+}
+
+fn pass_filter(high: bool, cutoff: f64, sample_rate: f64) -> Biquad {
+    let w0 = 2.0 * PI * cutoff / sample_rate;
+    let (sin, cos) = w0.sin_cos();
+    let alpha = sin / (2.0 * std::f64::consts::FRAC_1_SQRT_2);
+    let a = [1.0 + alpha, -2.0 * cos, 1.0 - alpha];
+    if high {
+        Biquad::new([(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0], a)
+    } else {
+        Biquad::new([(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0], a)
+    }
+}
+
+fn peaking(freq: f64, gain_db: f64, q: f64, sample_rate: f64) -> Biquad {
+    let a = 10f64.powf(gain_db.clamp(-24.0, 24.0) / 40.0);
+    let w0 = 2.0 * PI * freq / sample_rate;
+    let (sin, cos) = w0.sin_cos();
+    let alpha = sin / (2.0 * q);
+    Biquad::new(
+        [1.0 + alpha * a, -2.0 * cos, 1.0 - alpha * a],
+        [1.0 + alpha / a, -2.0 * cos, 1.0 - alpha / a],
+    )
+}
+
+fn shelf(high: bool, freq: f64, gain_db: f64, sample_rate: f64) -> Biquad {
+    let a = 10f64.powf(gain_db.clamp(-24.0, 24.0) / 40.0);
+    let w0 = 2.0 * PI * freq / sample_rate;
+    let (sin, cos) = w0.sin_cos();
+    // shelf slope of 1
+    let beta = 2.0 * a.sqrt() * sin / 2.0 * std::f64::consts::SQRT_2;
+    if high {
+        Biquad::new(
+            [
+                a * ((a + 1.0) + (a - 1.0) * cos + beta),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                a * ((a + 1.0) + (a - 1.0) * cos - beta),
+            ],
+            [
+                (a + 1.0) - (a - 1.0) * cos + beta,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                (a + 1.0) - (a - 1.0) * cos - beta,
+            ],
+        )
+    } else {
+        Biquad::new(
+            [
+                a * ((a + 1.0) - (a - 1.0) * cos + beta),
+                2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                a * ((a + 1.0) - (a - 1.0) * cos - beta),
+            ],
+            [
+                (a + 1.0) + (a - 1.0) * cos + beta,
+                -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                (a + 1.0) + (a - 1.0) * cos - beta,
+            ],
+        )
+    }
+}
+
+struct Bitcrush {
+    levels: f32,
+    downsample: u32,
+    held: Vec<f32>,
+    counter: u32,
+}
+
+impl Bitcrush {
+    fn new(effect: &Effect, channels: usize) -> Self {
+        let mut crush = Self { levels: 1.0, downsample: 1, held: vec![0.0; channels], counter: 0 };
+        crush.update(effect);
+        crush
+    }
+}
+
+impl FrameProcessor for Bitcrush {
+    fn process(&mut self, frame: &mut [f32]) {
+        if self.counter == 0 {
+            for (held, sample) in self.held.iter_mut().zip(frame.iter()) {
+                *held = (sample * self.levels).round() / self.levels;
+            }
+        }
+        self.counter = (self.counter + 1) % self.downsample;
+        frame.copy_from_slice(&self.held[..frame.len()]);
+    }
+
+    fn update(&mut self, effect: &Effect) -> bool {
+        let Effect::Bitcrush { bits, downsample } = effect else { return false };
+        self.levels = 2f32.powi((*bits).clamp(1, 16) as i32 - 1);
+        self.downsample = (*downsample).clamp(1, 64);
+        self.counter %= self.downsample;
+        true
+    }
+
+    fn reset(&mut self) {
+        self.held.fill(0.0);
+        self.counter = 0;
+    }
+}
+
+// tanh soft clipping, normalized so full scale in stays full scale out
+struct Distortion {
+    drive: f32,
+    mix: f32,
+}
+
+impl Distortion {
+    fn new(effect: &Effect) -> Self {
+        let mut distortion = Self { drive: 1.0, mix: 0.0 };
+        distortion.update(effect);
+        distortion
+    }
+}
+
+impl FrameProcessor for Distortion {
+    fn process(&mut self, frame: &mut [f32]) {
+        let norm = self.drive.tanh();
+        for sample in frame.iter_mut() {
+            let shaped = (*sample * self.drive).tanh() / norm;
+            *sample = *sample * (1.0 - self.mix) + shaped * self.mix;
+        }
+    }
+
+    fn update(&mut self, effect: &Effect) -> bool {
+        let Effect::Distortion { drive, mix } = effect else { return false };
+        self.drive = drive.clamp(1.0, 50.0);
+        self.mix = mix.clamp(0.0, 1.0);
+        true
+    }
+
+    fn reset(&mut self) {}
+}
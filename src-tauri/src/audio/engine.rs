@@ -1,22 +1,46 @@
-use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, atomic::{AtomicU32, Ordering}},
     sync::mpsc::{self, Sender, Receiver, RecvTimeoutError},
     thread,
-    time::{Instant, Duration},
+    time::{Duration, Instant},
 };
-use tracing::info;
+use tracing::{error, info, warn};
 use once_cell::sync::OnceCell;
-use rodio::Source;
-use crate::audio::{AudioManager, get_audio_manager};
+use tauri::Emitter;
+use uuid::Uuid;
+use crate::audio::{AudioBackend, ChannelMode, CpalBackend, DeviceMixer, DuckedSource, Effect, EffectChainSource, EffectsControl, SeekSlot, get_sample_cache, LoopRegion, MicInput, MicSource, OutputRoute, VIRTUAL_ROUTE, PlaybackCursor, SymphoniaAudioSource, VoiceControl};
+
+pub const DEFAULT_PROGRESS_INTERVAL_MS: u32 = 250;
+
+static EVENT_HANDLE: OnceCell<tauri::AppHandle> = OnceCell::new();
+
+// the engine starts before tauri, events are dropped until the app hands over its handle
+pub fn set_event_handle(handle: tauri::AppHandle) {
+    let _ = EVENT_HANDLE.set(handle);
+}
+
+pub(crate) fn emit_event<S: Serialize + Clone>(event: &str, payload: S) {
+    if let Some(handle) = EVENT_HANDLE.get() {
+        if let Err(e) = handle.emit(event, payload) {
+            error!("Failed to emit {}: {}", event, e);
+        }
+    }
+}
 
 //ik this looks dumb. but i had an old implementation
 fn combine_volume(volume1: f32, volume2: f32) -> f32 {
     volume1.max(0.0) * volume2.max(0.0)
 }
 
+fn with_jitter(jitter_rate: Option<f32>, mut effects: Vec<Effect>) -> Vec<Effect> {
+    if let Some(rate) = jitter_rate {
+        effects.insert(0, Effect::Speed { rate, preserve_pitch: false });
+    }
+    effects
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AudioDevice {
     pub name: String,
@@ -30,53 +54,325 @@ pub struct AudioSettings {
     pub output_device: Option<String>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlayMode {
+    // stop whatever of this sound is playing and start over
+    #[default]
+    Restart,
+    // every trigger adds another voice
+    Overlap,
+    // a trigger while playing stops it instead
+    Toggle,
+    // triggers are dropped until the sound is done
+    IgnoreWhilePlaying,
+    // plays only while the hotkey is held down
+    Hold,
+}
+
+impl PlayMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PlayMode::Restart => "restart",
+            PlayMode::Overlap => "overlap",
+            PlayMode::Toggle => "toggle",
+            PlayMode::IgnoreWhilePlaying => "ignore_while_playing",
+            PlayMode::Hold => "hold",
+        }
+    }
+
+    pub fn from_db(value: Option<&str>) -> Self {
+        match value {
+            Some("overlap") => PlayMode::Overlap,
+            Some("toggle") => PlayMode::Toggle,
+            Some("ignore_while_playing") => PlayMode::IgnoreWhilePlaying,
+            Some("hold") => PlayMode::Hold,
+            _ => PlayMode::Restart,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoopMode {
+    #[default]
+    Off,
+    // from the start position to the end of the file
+    File,
+    // between loop_start and loop_end
+    Region,
+}
+
+impl LoopMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoopMode::Off => "off",
+            LoopMode::File => "file",
+            LoopMode::Region => "region",
+        }
+    }
+
+    pub fn from_db(value: Option<&str>) -> Self {
+        match value {
+            Some("file") => LoopMode::File,
+            Some("region") => LoopMode::Region,
+            _ => LoopMode::Off,
+        }
+    }
+}
+
+// non-destructive trim, the end position is in seconds from the start of the file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Trim {
+    pub end_position: Option<f32>,
+    pub fade_in_ms: u32,
+    pub fade_out_ms: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StealPolicy {
+    // cut the voice that has been playing the longest
+    #[default]
+    Oldest,
+    Quietest,
+    // cut the lowest priority voice, never one above the new sound
+    LowestPriority,
+    // drop the new sound instead
+    Refuse,
+}
+
+impl StealPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StealPolicy::Oldest => "oldest",
+            StealPolicy::Quietest => "quietest",
+            StealPolicy::LowestPriority => "lowest_priority",
+            StealPolicy::Refuse => "refuse",
+        }
+    }
+
+    pub fn from_db(value: Option<&str>) -> Self {
+        match value {
+            Some("quietest") => StealPolicy::Quietest,
+            Some("lowest_priority") => StealPolicy::LowestPriority,
+            Some("refuse") => StealPolicy::Refuse,
+            _ => StealPolicy::Oldest,
+        }
+    }
+}
+
+pub const DEFAULT_MAX_VOICES: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoiceLimit {
+    pub max_voices: usize,
+    pub policy: StealPolicy,
+}
+
+impl Default for VoiceLimit {
+    fn default() -> Self {
+        Self {
+            max_voices: DEFAULT_MAX_VOICES,
+            policy: StealPolicy::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayOptions {
+    pub start_position: Option<f32>,
+    pub sound_volume: f32,
+    // linear gain from loudness analysis, 1.0 when normalization is off
+    pub normalization_gain: f32,
+    pub local_only: bool,
+    pub play_mode: PlayMode,
+    pub loop_region: Option<LoopRegion>,
+    pub trim: Trim,
+    // starting this sound cuts off every other sound in the same group
+    pub choke_group: Option<String>,
+    // used by the lowest priority stealing policy, higher wins
+    pub priority: i32,
+    // route names to play on, None means every route
+    pub output_routes: Option<Vec<String>>,
+    pub effects: Vec<Effect>,
+    // read rate picked by pitch jitter for this trigger, kept in front of the chain through live edits
+    pub jitter_rate: Option<f32>,
+    // -1.0 is hard left, 1.0 hard right
+    pub pan: f32,
+    // the sound's categories, ducking tells music apart by them
+    pub categories: Vec<String>,
+}
+
+impl Default for PlayOptions {
+    fn default() -> Self {
+        Self {
+            start_position: None,
+            sound_volume: 1.0,
+            normalization_gain: 1.0,
+            local_only: false,
+            play_mode: PlayMode::default(),
+            loop_region: None,
+            trim: Trim::default(),
+            choke_group: None,
+            priority: 0,
+            output_routes: None,
+            effects: Vec::new(),
+            jitter_rate: None,
+            pan: 0.0,
+            categories: Vec::new(),
+        }
+    }
+}
+
+// deserializable so offline render scripts can be written as JSON
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum AudioCommand {
     Play {
         file_path: String,
         sound_id: String,
-        start_position: Option<f32>,
-        sound_volume: f32,
-        local_only: bool,
+        options: PlayOptions,
     },
     Stop {
         sound_id: String,
     },
+    StopVoice {
+        voice_id: String,
+    },
     StopAll,
+    Pause {
+        sound_id: String,
+    },
+    Resume {
+        sound_id: String,
+    },
+    PauseAll,
+    ResumeAll,
+    Seek {
+        sound_id: String,
+        position: f32,
+    },
     UpdateSoundVolume {
         sound_id: String,
         sound_volume: f32,
     },
+    UpdatePan {
+        sound_id: String,
+        pan: f32,
+    },
     UpdateDeviceVolumes,
+    // swaps the effect chain of every voice of the sound, taking effect mid-playback
+    UpdateEffects {
+        sound_id: String,
+        effects: Vec<Effect>,
+    },
+    // the mic joins the virtual device's mix as an always-on voice
+    #[serde(skip)]
+    AttachInput {
+        input: MicInput,
+    },
+    DetachInput,
+    // the device went away or came back, either way its stream is dead
+    ResetDevice {
+        device_name: String,
+    },
     Shutdown,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct DevicePosition {
+    pub device: String,
+    pub position: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SoundErrorEvent {
+    pub sound_id: String,
+    pub device: Option<String>,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayingSoundInfo {
+    pub sound_id: String,
+    pub voice_id: String,
+    pub position: f32,
+    pub duration: Option<f32>,
+    pub paused: bool,
+    pub priority: i32,
+    pub devices: Vec<DevicePosition>,
+}
+
+// the part of a playing voice other threads can look at, positions come straight from the decoders
+struct PlayingSound {
+    sound_id: String,
+    started: Instant,
+    duration: Option<f32>,
+    paused: bool,
+    priority: i32,
+    cursors: Vec<(String, Arc<PlaybackCursor>)>,
+}
+
+impl PlayingSound {
+    // the first route is the virtual device whenever one is set, which is what listeners hear
+    fn position(&self) -> f32 {
+        self.cursors.first().map(|(_, cursor)| cursor.position()).unwrap_or(0.0)
+    }
+
+    fn info(&self, voice_id: &str) -> PlayingSoundInfo {
+        PlayingSoundInfo {
+            sound_id: self.sound_id.clone(),
+            voice_id: voice_id.to_string(),
+            position: self.position(),
+            duration: self.duration,
+            paused: self.paused,
+            priority: self.priority,
+            devices: self
+                .cursors
+                .iter()
+                .map(|(device, cursor)| DevicePosition { device: device.clone(), position: cursor.position() })
+                .collect(),
+        }
+    }
+}
+
+struct VoiceOutput {
+    route: String,
+    device_name: String,
+    route_volume: f32,
+    control: Arc<VoiceControl>,
+    // None when the voice plays from memory
+    seek_slot: Option<Arc<SeekSlot>>,
+}
+
 struct SoundInstance {
-    sinks: Vec<rodio::Sink>,
-    _streams: Vec<(rodio::OutputStream, rodio::OutputStreamHandle)>,//for keep alive
-    device_volumes: Vec<f32>,
+    sound_id: String,
+    file_path: String,
+    voices: Vec<VoiceOutput>,
     sound_volume: f32,
+    // loudness normalization, applied on top of the sound volume
+    normalization_gain: f32,
+    fade_out_ms: u32,
+    choke_group: Option<String>,
+    priority: i32,
+    // shared by the voice on every route so an edit reaches all of them
+    effects: Arc<EffectsControl>,
+    jitter_rate: Option<f32>,
+    categories: Vec<String>,
+    started: Instant,
 }
 
 impl SoundInstance {
-    fn new(
-        sinks: Vec<rodio::Sink>,
-        streams: Vec<(rodio::OutputStream, rodio::OutputStreamHandle)>,
-        device_volumes: Vec<f32>,
-        sound_volume: f32,
-    ) -> Self {
-        Self {
-            sinks,
-            _streams: streams,
-            device_volumes,
-            sound_volume,
-        }
+    fn effective_volume(&self) -> f32 {
+        self.sound_volume * self.normalization_gain
     }
 
     fn apply_volume_updates(&mut self) {
-        for (i, sink) in self.sinks.iter_mut().enumerate() {
-            let device_volume = self.device_volumes.get(i).unwrap_or(&1.0);
-            let combined_volume = combine_volume(*device_volume, self.sound_volume);
-            sink.set_volume(combined_volume);
+        for voice in &self.voices {
+            let combined_volume = combine_volume(voice.route_volume, self.effective_volume());
+            voice.control.set_volume(combined_volume);
         }
     }
 
@@ -85,222 +381,809 @@ impl SoundInstance {
         self.apply_volume_updates();
     }
 
-    fn update_device_volumes(&mut self, virtual_volume: f32, output_volume: f32) {
-        let device_count = self.device_volumes.len();
-        for (i, device_volume) in self.device_volumes.iter_mut().enumerate() {
-            if i == 0 && device_count > 1 {
-                *device_volume = virtual_volume;
-            } else if i == 1 || device_count == 1 {
-                *device_volume = output_volume;
+    fn update_pan(&self, pan: f32) {
+        for voice in &self.voices {
+            voice.control.set_pan(pan);
+        }
+    }
+
+    // voices are matched to routes by name, local-only and fallback voices aren't in the table and keep their volume
+    fn update_device_volumes(&mut self, routes: &[OutputRoute]) {
+        for voice in &mut self.voices {
+            if let Some(route) = routes.iter().find(|route| route.name == voice.route) {
+                voice.route_volume = route.effective_volume();
+                voice.control.set_channel_mode(route.channel_mode);
             }
         }
         self.apply_volume_updates();
     }
 
     fn is_finished(&self) -> bool {
-        self.sinks.iter().all(|sink| sink.empty())
+        self.voices.iter().all(|voice| voice.control.is_finished())
+    }
+
+    // only what reaches the virtual device is talked over, local previews never duck
+    fn is_audible_on_virtual(&self) -> bool {
+        self.voices
+            .iter()
+            .any(|voice| voice.route == VIRTUAL_ROUTE && !voice.control.is_paused() && !voice.control.is_finished())
     }
 
     fn stop(&self) {
-        for sink in &self.sinks {
-            sink.stop();
+        for voice in &self.voices {
+            voice.control.stop();
+        }
+    }
+
+    // stop as the user hears it, using the sound's fade-out if it has one
+    fn fade_out(&self) {
+        for voice in &self.voices {
+            voice.control.stop_with_fade(self.fade_out_ms);
+        }
+    }
+
+    fn set_paused(&self, paused: bool) {
+        for voice in &self.voices {
+            voice.control.set_paused(paused);
+        }
+    }
+
+    // a streamed voice gets its file opened at the target here, so the device thread only swaps decoders
+    fn seek(&self, position: f32) {
+        for voice in &self.voices {
+            if let Some(slot) = &voice.seek_slot {
+                if let Err(e) = slot.prepare(&self.file_path, position) {
+                    tracing::error!("Failed to seek {} on {} to {:.3}s: {}", self.sound_id, voice.device_name, position, e);
+                    continue;
+                }
+            }
+            voice.control.seek(position);
         }
     }
 }
 
-fn create_stream_and_sink(device: Option<&cpal::Device>, sound_id: &str) -> Result<(rodio::OutputStream, rodio::OutputStreamHandle, rodio::Sink), String> {
-    let (stream, handle) = if let Some(device) = device {
-        rodio::OutputStream::try_from_device(device)
-            .map_err(|e| format!("Failed to create output stream from device: {}", e))?
-    } else {
-        rodio::OutputStream::try_default()
-            .map_err(|e| format!("Failed to create default output stream: {}", e))?
-    };
-    let sink = rodio::Sink::try_new(&handle)
-        .map_err(|e| format!("Failed to create sink for {}: {}", sound_id, e))?;
-    Ok((stream, handle, sink))
+struct PlaybackRoute {
+    route: String,
+    device_name: String,
+    volume: f32,
+    channel_mode: ChannelMode,
 }
 
-fn try_add_device_stream(
-    device: Option<&cpal::Device>,
-    sound_id: &str,
-    new_streams: &mut Vec<(rodio::OutputStream, rodio::OutputStreamHandle)>,
-    new_sinks: &mut Vec<rodio::Sink>,
-    device_name: &str,
-    _device_volume: f32
+fn route_device(
+    backend: &dyn AudioBackend,
+    route: &str,
+    device_name: Option<&str>,
+    volume: f32,
+    channel_mode: ChannelMode,
+    mixers: &mut HashMap<String, DeviceMixer>,
+    routes: &mut Vec<PlaybackRoute>,
 ) {
-    if let Ok((stream, handle, sink)) = create_stream_and_sink(device, sound_id) {
-        new_streams.push((stream, handle));
-        new_sinks.push(sink);
-    } else {
-        tracing::error!("Failed to create {} device stream for {}", device_name, sound_id);
+    let Some(device_name) = device_name.map(str::to_string).or_else(|| backend.default_output()) else {
+        tracing::error!("No device available for route {}", route);
+        return;
+    };
+    // two routes on the same device would play the sound twice
+    if let Some(other) = routes.iter().find(|r| r.device_name == device_name) {
+        warn!("Skipping route {} for this sound, {} already plays it on {}", route, other.route, device_name);
+        return;
+    }
+    if !mixers.contains_key(&device_name) {
+        match backend.open_mixer(&device_name, backend.route_limiter(route)) {
+            Ok(mixer) => {
+                mixers.insert(device_name.clone(), mixer);
+            }
+            Err(e) => {
+                tracing::error!("Failed to open {} device mixer: {}", route, e);
+                return;
+            }
+        }
     }
+    routes.push(PlaybackRoute { route: route.to_string(), device_name, volume, channel_mode });
 }
 
+// picks the mixers a sound plays on, opening any that aren't running yet
 fn setup_devices_for_playback(
-    manager: &AudioManager, 
-    sound_id: &str, 
-    local_only: bool
-) -> (Vec<rodio::Sink>, Vec<(rodio::OutputStream, rodio::OutputStreamHandle)>, Vec<f32>) {
-    let mut new_sinks = Vec::new();
-    let mut new_streams = Vec::new();
-    let mut device_volumes = Vec::new();
-    
+    backend: &dyn AudioBackend,
+    local_only: bool,
+    output_routes: Option<&[String]>,
+    mixers: &mut HashMap<String, DeviceMixer>,
+) -> Vec<PlaybackRoute> {
+    let mut routes = Vec::new();
+
     if local_only {
-        try_add_device_stream(None, sound_id, &mut new_streams, &mut new_sinks, "default", 1.0);
-        device_volumes.push(1.0);
+        route_device(backend, "local", None, 1.0, ChannelMode::Stereo, mixers, &mut routes);
     } else {
-        let virtual_device = manager.get_virtual_device();
-        let output_device = manager.get_output_device();
-        let virtual_volume = manager.get_virtual_volume();
-        let output_volume = manager.get_output_volume();
-        
-        if let Some(device) = virtual_device {
-            try_add_device_stream(Some(&device), sound_id, &mut new_streams, &mut new_sinks, "virtual", virtual_volume);
-            device_volumes.push(virtual_volume);
+        for route in backend.routes() {
+            if output_routes.is_some_and(|selected| !selected.contains(&route.name)) {
+                continue;
+            }
+            let Some(device_name) = route.device.as_deref() else { continue };
+            route_device(backend, &route.name, Some(device_name), route.effective_volume(), route.channel_mode, mixers, &mut routes);
         }
-        
-        if let Some(device) = output_device {
-            try_add_device_stream(Some(&device), sound_id, &mut new_streams, &mut new_sinks, "output", output_volume);
-            device_volumes.push(output_volume);
+
+        // a sound limited to specific routes stays silent rather than leaking onto the default device
+        if routes.is_empty() && output_routes.is_none() {
+            route_device(backend, "default fallback", None, 1.0, ChannelMode::Stereo, mixers, &mut routes);
         }
-        
-        if new_sinks.is_empty() {
-            try_add_device_stream(None, sound_id, &mut new_streams, &mut new_sinks, "default fallback", 1.0);
-            device_volumes.push(1.0);
+    }
+
+    routes
+}
+
+// drops mixer streams for devices that are no longer routed and have nothing playing on them
+fn prune_idle_mixers(
+    backend: &dyn AudioBackend,
+    mixers: &mut HashMap<String, DeviceMixer>,
+    sound_instances: &HashMap<String, SoundInstance>,
+    input: Option<&InputVoice>,
+) {
+    let mut routed: Vec<String> = backend.routes().into_iter().filter_map(|route| route.device).collect();
+    routed.extend(backend.default_output());
+    routed.extend(input.map(|input| input.device_name.clone()));
+    mixers.retain(|name, _| {
+        let keep = routed.contains(name)
+            || sound_instances.values().any(|instance| instance.voices.iter().any(|v| v.device_name == *name));
+        if !keep {
+            info!("Closing idle mixer stream for {}", name);
         }
+        keep
+    });
+}
+
+// drops a voice from both maps and tells the frontend it was stopped, the caller silences it
+fn remove_voice(
+    voice_id: &str,
+    sound_instances: &mut HashMap<String, SoundInstance>,
+    playing: &mut HashMap<String, PlayingSound>,
+) -> Option<SoundInstance> {
+    if let Some(playing_sound) = playing.remove(voice_id) {
+        emit_event("sound-stopped", playing_sound.info(voice_id));
+    }
+    sound_instances.remove(voice_id)
+}
+
+// drops the mixer of a device whose stream died, it's reopened on the next play.
+// voices left with no device to play on are stopped
+fn reset_device(
+    device_name: &str,
+    mixers: &mut HashMap<String, DeviceMixer>,
+    sound_instances: &mut HashMap<String, SoundInstance>,
+    playing_thread: &Arc<Mutex<HashMap<String, PlayingSound>>>,
+) {
+    if mixers.remove(device_name).is_some() {
+        info!("Closed mixer stream for {} after a device change", device_name);
+    }
+    let mut playing = playing_thread.lock().expect("Lock poisoned");
+    let mut orphaned = Vec::new();
+    for (voice_id, instance) in sound_instances.iter_mut() {
+        instance.voices.retain(|voice| voice.device_name != device_name);
+        if let Some(playing_sound) = playing.get_mut(voice_id) {
+            playing_sound.cursors.retain(|(device, _)| device != device_name);
+        }
+        if instance.voices.is_empty() {
+            orphaned.push(voice_id.clone());
+        }
+    }
+    for voice_id in orphaned {
+        remove_voice(&voice_id, sound_instances, &mut playing);
     }
-    
-    (new_sinks, new_streams, device_volumes)
 }
 
-fn handle_play_command(
-    file_path: &str,
+// stops every voice of a sound, returns how many there were
+fn stop_sound_voices(
+    sound_id: &str,
+    sound_instances: &mut HashMap<String, SoundInstance>,
+    playing_thread: &Arc<Mutex<HashMap<String, PlayingSound>>>,
+) -> usize {
+    let voice_ids: Vec<String> = sound_instances
+        .iter()
+        .filter(|(_, instance)| instance.sound_id == sound_id)
+        .map(|(voice_id, _)| voice_id.clone())
+        .collect();
+    let mut playing = playing_thread.lock().expect("Lock poisoned");
+    for voice_id in &voice_ids {
+        if let Some(instance) = remove_voice(voice_id, sound_instances, &mut playing) {
+            instance.fade_out();
+        }
+    }
+    voice_ids.len()
+}
+
+// voices of other sounds in the group fade out with their own fade-out, if they have one
+fn choke_group_voices(
+    group: &str,
     sound_id: &str,
-    start_position: Option<f32>,
-    sound_volume: f32,
-    local_only: bool,
     sound_instances: &mut HashMap<String, SoundInstance>,
-    playing_thread: &Arc<Mutex<HashMap<String, (Instant, f32, f32)>>>,
+    playing_thread: &Arc<Mutex<HashMap<String, PlayingSound>>>,
 ) {
-    if let Some(existing_instance) = sound_instances.remove(sound_id) {
-        existing_instance.stop();
-    }
-    
-    let manager = get_audio_manager();
-    let (mut new_sinks, new_streams, new_device_volumes) = setup_devices_for_playback(&manager, sound_id, local_only);
-    
-    let source_result = crate::audio::SymphoniaAudioSource::new(file_path, start_position.unwrap_or(0.0));
-    let total_duration = if let Ok(ref src) = source_result {
-        src.total_duration().map(|d| d.as_secs_f32()).unwrap_or(0.0)
-    } else { 0.0 };
-    let used_start_position = start_position.unwrap_or(0.0);
-    let remaining_duration = if total_duration > 0.0 { (total_duration - used_start_position).max(0.0) } else { 0.0 };
-    info!("Sound {} duration calculation: total={:.2}s, start_pos={:.2}s, remaining={:.2}s", 
-          sound_id, total_duration, used_start_position, remaining_duration);
-    
-    let buffered_source = match source_result {
-        Ok(src) => src.buffered(),
-        Err(e) => {
-            tracing::error!("Failed to create audio source for {}: {}", sound_id, e);
-            return;
+    let voice_ids: Vec<String> = sound_instances
+        .iter()
+        .filter(|(_, instance)| instance.sound_id != sound_id && instance.choke_group.as_deref() == Some(group))
+        .map(|(voice_id, _)| voice_id.clone())
+        .collect();
+    let mut playing = playing_thread.lock().expect("Lock poisoned");
+    for voice_id in &voice_ids {
+        if let Some(instance) = remove_voice(voice_id, sound_instances, &mut playing) {
+            info!("Choking sound {} (group: {})", instance.sound_id, group);
+            instance.fade_out();
         }
-    };
-    
-    for (i, sink) in new_sinks.iter_mut().enumerate() {
-        let device_volume = new_device_volumes.get(i).unwrap_or(&1.0);
-        let combined_volume = combine_volume(*device_volume, sound_volume);
-        sink.set_volume(combined_volume);
-        sink.append(buffered_source.clone());
-    }
-    
-    let instance = SoundInstance::new(
-        new_sinks,
-        new_streams,
-        new_device_volumes,
-        sound_volume,
-    );
-    
-    sound_instances.insert(sound_id.to_string(), instance);
-    // Store remaining duration; get_playing_sounds and get_playback_position treat it as remaining time
-    playing_thread.lock().expect("Lock poisoned").insert(sound_id.to_string(), (Instant::now(), remaining_duration, used_start_position));
-    info!("Started playing sound: {} with volume: {} (local_only: {})", sound_id, sound_volume, local_only);
+    }
 }
 
+// steals voices until a new one fits under the limit, false means the new voice should be dropped
+fn make_room_for_voice(
+    voice_limit: VoiceLimit,
+    priority: i32,
+    sound_instances: &mut HashMap<String, SoundInstance>,
+    playing_thread: &Arc<Mutex<HashMap<String, PlayingSound>>>,
+) -> bool {
+    loop {
+        let live: Vec<(&String, &SoundInstance)> = sound_instances
+            .iter()
+            .filter(|(_, instance)| !instance.is_finished())
+            .collect();
+        if live.len() < voice_limit.max_voices.max(1) {
+            return true;
+        }
+        let victim = match voice_limit.policy {
+            StealPolicy::Refuse => None,
+            StealPolicy::Oldest => live
+                .iter()
+                .min_by_key(|(_, instance)| instance.started),
+            StealPolicy::Quietest => live
+                .iter()
+                .min_by(|(_, a), (_, b)| a.effective_volume().total_cmp(&b.effective_volume())),
+            StealPolicy::LowestPriority => live
+                .iter()
+                .filter(|(_, instance)| instance.priority <= priority)
+                .min_by_key(|(_, instance)| (instance.priority, instance.started)),
+        };
+        let Some(voice_id) = victim.map(|(voice_id, _)| (*voice_id).clone()) else {
+            return false;
+        };
+        let mut playing = playing_thread.lock().expect("Lock poisoned");
+        if let Some(instance) = remove_voice(&voice_id, sound_instances, &mut playing) {
+            info!("Stealing voice {} of sound {} ({})", voice_id, instance.sound_id, voice_limit.policy.as_str());
+            instance.stop();
+        }
+    }
+}
+
+// returns false if the sound has no voices to pause or resume
+fn set_sound_paused(
+    sound_id: &str,
+    paused: bool,
+    sound_instances: &HashMap<String, SoundInstance>,
+    playing_thread: &Arc<Mutex<HashMap<String, PlayingSound>>>,
+) -> bool {
+    let mut playing = playing_thread.lock().expect("Lock poisoned");
+    let mut found = false;
+    for (voice_id, instance) in sound_instances.iter().filter(|(_, instance)| instance.sound_id == sound_id) {
+        instance.set_paused(paused);
+        if let Some(playing_sound) = playing.get_mut(voice_id) {
+            playing_sound.paused = paused;
+        }
+        found = true;
+    }
+    found
+}
+
+
 fn cleanup_finished_sounds(
     sound_instances: &mut HashMap<String, SoundInstance>,
-    playing_thread: &Arc<Mutex<HashMap<String, (Instant, f32, f32)>>>,
+    playing_thread: &Arc<Mutex<HashMap<String, PlayingSound>>>,
 ) {
     let mut to_remove = vec![];
     for (id, instance) in sound_instances.iter() {
         if instance.is_finished() {
-            info!("Sound {} finished playing on all {} devices, removing from active sounds", id, instance.sinks.len());
+            info!("Voice {} of sound {} finished playing on all {} devices, removing from active sounds", id, instance.sound_id, instance.voices.len());
             to_remove.push(id.clone());
         }
     }
     for id in to_remove {
         sound_instances.remove(&id);
-        playing_thread.lock().expect("Lock poisoned").remove(&id);
-        info!("Removed sound {} from all tracking structures", id);
+        if let Some(playing_sound) = playing_thread.lock().expect("Lock poisoned").remove(&id) {
+            emit_event("sound-finished", playing_sound.info(&id));
+        }
+        info!("Removed voice {} from all tracking structures", id);
+    }
+}
+
+// the mic while input capture runs, an always-on voice in the virtual route's mix
+struct InputVoice {
+    device_name: String,
+    control: Arc<VoiceControl>,
+}
+
+// the engine's state, owned by the audio thread or by an offline render
+pub(crate) struct EngineCore {
+    backend: Arc<dyn AudioBackend>,
+    sound_instances: HashMap<String, SoundInstance>,
+    mixers: HashMap<String, DeviceMixer>,
+    playing: Arc<Mutex<HashMap<String, PlayingSound>>>,
+    input: Option<InputVoice>,
+}
+
+impl EngineCore {
+    pub(crate) fn new(backend: Arc<dyn AudioBackend>) -> Self {
+        Self::with_playing(backend, Arc::new(Mutex::new(HashMap::new())))
+    }
+
+    fn with_playing(
+        backend: Arc<dyn AudioBackend>,
+        playing: Arc<Mutex<HashMap<String, PlayingSound>>>,
+    ) -> Self {
+        Self {
+            backend,
+            sound_instances: HashMap::new(),
+            mixers: HashMap::new(),
+            playing,
+            input: None,
+        }
+    }
+
+    // the mic shares the virtual device's stream, limiter and route volume with the sounds played there
+    fn attach_input(&mut self, input: MicInput) {
+        self.detach_input();
+        let Some(route) = self
+            .backend
+            .routes()
+            .into_iter()
+            .find(|route| route.name == VIRTUAL_ROUTE)
+        else {
+            tracing::error!("No virtual route to pass the mic through");
+            return;
+        };
+        let Some(device_name) = route.device.as_deref() else {
+            tracing::error!("No virtual device set, the mic is not passed through");
+            return;
+        };
+        let mut routes = Vec::new();
+        route_device(
+            self.backend.as_ref(),
+            &route.name,
+            Some(device_name),
+            route.effective_volume(),
+            route.channel_mode,
+            &mut self.mixers,
+            &mut routes,
+        );
+        let Some(PlaybackRoute {
+            device_name,
+            volume,
+            channel_mode,
+            ..
+        }) = routes.pop()
+        else {
+            return;
+        };
+        let Some(mixer) = self.mixers.get(&device_name) else {
+            return;
+        };
+
+        let control = Arc::new(VoiceControl::new(volume));
+        control.set_channel_mode(channel_mode);
+        info!(
+            "Passing the mic through on {} ({} ch @ {} Hz)",
+            device_name, input.channels, input.sample_rate
+        );
+        mixer.add_voice(MicSource::new(input, mixer.sample_rate()), control.clone());
+        self.input = Some(InputVoice {
+            device_name,
+            control,
+        });
+    }
+
+    fn detach_input(&mut self) {
+        if let Some(input) = self.input.take() {
+            input.control.stop();
+            info!("Stopped passing the mic through on {}", input.device_name);
+        }
+    }
+
+    pub(crate) fn is_idle(&self) -> bool {
+        self.sound_instances.is_empty()
+    }
+
+    pub(crate) fn cleanup_finished(&mut self) {
+        cleanup_finished_sounds(&mut self.sound_instances, &self.playing);
+        self.update_ducking();
+    }
+
+    // ducking holds while any sound outside the music category plays on the virtual device
+    fn update_ducking(&self) {
+        let ducking = self.backend.ducking();
+        let config = ducking.config();
+        let active = config.enabled
+            && self.sound_instances.values().any(|instance| {
+                !config.is_music(&instance.categories) && instance.is_audible_on_virtual()
+            });
+        if active != ducking.is_active() {
+            info!("Ducking {}", if active { "started" } else { "released" });
+            ducking.set_active(active);
+        }
+    }
+
+    fn play(
+        &mut self,
+        file_path: &str,
+        sound_id: &str,
+        options: PlayOptions,
+        voice_limit: VoiceLimit,
+    ) {
+        let Self {
+            backend,
+            sound_instances,
+            mixers,
+            playing: playing_thread,
+            input,
+        } = self;
+        let PlayOptions {
+            start_position,
+            sound_volume,
+            normalization_gain,
+            local_only,
+            play_mode,
+            loop_region,
+            trim,
+            choke_group,
+            priority,
+            output_routes,
+            effects,
+            jitter_rate,
+            pan,
+            categories,
+        } = options;
+        let already_playing = sound_instances
+            .values()
+            .any(|instance| instance.sound_id == sound_id && !instance.is_finished());
+        match play_mode {
+            PlayMode::Restart | PlayMode::Hold => {
+                stop_sound_voices(sound_id, sound_instances, playing_thread);
+            }
+            PlayMode::Toggle if already_playing => {
+                stop_sound_voices(sound_id, sound_instances, playing_thread);
+                info!("Toggled sound {} off", sound_id);
+                return;
+            }
+            PlayMode::IgnoreWhilePlaying if already_playing => {
+                info!("Ignoring trigger for {}, it is still playing", sound_id);
+                return;
+            }
+            _ => {}
+        }
+        if let Some(group) = &choke_group {
+            choke_group_voices(group, sound_id, sound_instances, playing_thread);
+        }
+        if !make_room_for_voice(voice_limit, priority, sound_instances, playing_thread) {
+            info!(
+                "Voice limit of {} reached, not playing {} ({})",
+                voice_limit.max_voices,
+                sound_id,
+                voice_limit.policy.as_str()
+            );
+            return;
+        }
+
+        prune_idle_mixers(backend.as_ref(), mixers, sound_instances, input.as_ref());
+        let routes = setup_devices_for_playback(
+            backend.as_ref(),
+            local_only,
+            output_routes.as_deref(),
+            mixers,
+        );
+
+        let start_position = start_position.unwrap_or(0.0);
+        // a loop wraps by resetting an index, so a looping sound is always played from memory
+        let cached = match loop_region {
+            Some(_) => get_sample_cache()
+                .get_or_decode(file_path)
+                .map_err(|e| {
+                    tracing::error!(
+                        "Failed to decode looping sound {} into memory, it won't loop: {}",
+                        sound_id,
+                        e
+                    )
+                })
+                .ok(),
+            None => get_sample_cache().get(file_path),
+        };
+        let effects = Arc::new(EffectsControl::new(with_jitter(jitter_rate, effects)));
+        let mut voices = Vec::new();
+        let mut cursors = Vec::new();
+        let mut duration = None;
+        for PlaybackRoute {
+            route,
+            device_name,
+            volume: route_volume,
+            channel_mode,
+        } in routes
+        {
+            let Some(mixer) = mixers.get(&device_name) else {
+                continue;
+            };
+            // every device reads on its own so its position follows what that device has actually played
+            let opened = match &cached {
+                Some(audio) => Ok(SymphoniaAudioSource::from_decoded(
+                    audio.clone(),
+                    start_position,
+                )),
+                None => SymphoniaAudioSource::new(file_path, start_position),
+            };
+            let mut source = match opened {
+                Ok(src) => src,
+                Err(e) => {
+                    tracing::error!(
+                        "Failed to create audio source for {} on {}: {}",
+                        sound_id,
+                        device_name,
+                        e
+                    );
+                    emit_event(
+                        "sound-error",
+                        SoundErrorEvent {
+                            sound_id: sound_id.to_string(),
+                            device: Some(device_name),
+                            error: e.to_string(),
+                        },
+                    );
+                    continue;
+                }
+            };
+            source.set_trim(trim.end_position, trim.fade_in_ms, trim.fade_out_ms);
+            if let Some(region) = loop_region {
+                source.set_loop(region);
+            }
+            duration = duration.or(source.duration());
+            cursors.push((device_name.clone(), source.cursor()));
+            let seek_slot = source.seek_slot();
+            let control = Arc::new(VoiceControl::new(combine_volume(
+                route_volume,
+                sound_volume * normalization_gain,
+            )));
+            control.set_pan(pan);
+            control.set_channel_mode(channel_mode);
+            let source = DuckedSource::new(
+                EffectChainSource::new(source, effects.clone()),
+                backend.ducking(),
+                categories.clone(),
+            );
+            mixer.add_voice(source, control.clone());
+            voices.push(VoiceOutput {
+                route,
+                device_name,
+                route_volume,
+                control,
+                seek_slot,
+            });
+        }
+
+        if cached.is_none() {
+            get_sample_cache().load_in_background(file_path.to_string());
+        }
+
+        if voices.is_empty() {
+            tracing::error!("Sound {} could not be started on any device", sound_id);
+            emit_event(
+                "sound-error",
+                SoundErrorEvent {
+                    sound_id: sound_id.to_string(),
+                    device: None,
+                    error: "Sound could not be started on any device".to_string(),
+                },
+            );
+            return;
+        }
+
+        let instance = SoundInstance {
+            sound_id: sound_id.to_string(),
+            file_path: file_path.to_string(),
+            voices,
+            sound_volume,
+            normalization_gain,
+            fade_out_ms: trim.fade_out_ms,
+            choke_group,
+            priority,
+            effects,
+            jitter_rate,
+            categories,
+            started: Instant::now(),
+        };
+
+        let voice_id = Uuid::new_v4().to_string();
+        sound_instances.insert(voice_id.clone(), instance);
+        let playing_sound = PlayingSound {
+            sound_id: sound_id.to_string(),
+            started: Instant::now(),
+            duration,
+            paused: false,
+            priority,
+            cursors,
+        };
+        emit_event("sound-started", playing_sound.info(&voice_id));
+        playing_thread
+            .lock()
+            .expect("Lock poisoned")
+            .insert(voice_id.clone(), playing_sound);
+        info!("Started playing sound: {} as voice {} with volume: {} (local_only: {}, mode: {}, start: {:.3}s, duration: {:?})", sound_id, voice_id, sound_volume, local_only, play_mode.as_str(), start_position, duration);
+    }
+
+    // false once the engine has been shut down
+    pub(crate) fn handle_command(&mut self, cmd: AudioCommand, voice_limit: VoiceLimit) -> bool {
+        match cmd {
+            AudioCommand::Play {
+                file_path,
+                sound_id,
+                options,
+            } => {
+                self.play(&file_path, &sound_id, options, voice_limit);
+            }
+            AudioCommand::Stop { sound_id } => {
+                stop_sound_voices(&sound_id, &mut self.sound_instances, &self.playing);
+            }
+            AudioCommand::StopVoice { voice_id } => {
+                let mut playing = self.playing.lock().expect("Lock poisoned");
+                if let Some(instance) =
+                    remove_voice(&voice_id, &mut self.sound_instances, &mut playing)
+                {
+                    instance.fade_out();
+                }
+            }
+            AudioCommand::StopAll => {
+                let mut playing = self.playing.lock().expect("Lock poisoned");
+                let voice_ids: Vec<String> = self.sound_instances.keys().cloned().collect();
+                for voice_id in voice_ids {
+                    if let Some(instance) =
+                        remove_voice(&voice_id, &mut self.sound_instances, &mut playing)
+                    {
+                        instance.fade_out();
+                    }
+                }
+            }
+            AudioCommand::Pause { sound_id } => {
+                if set_sound_paused(&sound_id, true, &self.sound_instances, &self.playing) {
+                    info!("Paused sound: {}", sound_id);
+                }
+            }
+            AudioCommand::Resume { sound_id } => {
+                if set_sound_paused(&sound_id, false, &self.sound_instances, &self.playing) {
+                    info!("Resumed sound: {}", sound_id);
+                }
+            }
+            AudioCommand::PauseAll => {
+                for instance in self.sound_instances.values() {
+                    instance.set_paused(true);
+                }
+                for playing in self.playing.lock().expect("Lock poisoned").values_mut() {
+                    playing.paused = true;
+                }
+                info!("Paused all sounds");
+            }
+            AudioCommand::ResumeAll => {
+                for instance in self.sound_instances.values() {
+                    instance.set_paused(false);
+                }
+                for playing in self.playing.lock().expect("Lock poisoned").values_mut() {
+                    playing.paused = false;
+                }
+                info!("Resumed all sounds");
+            }
+            AudioCommand::Seek { sound_id, position } => {
+                for instance in self
+                    .sound_instances
+                    .values()
+                    .filter(|instance| instance.sound_id == sound_id)
+                {
+                    instance.seek(position.max(0.0));
+                }
+                info!("Seeking sound {} to {:.3}s", sound_id, position);
+            }
+            AudioCommand::UpdateSoundVolume {
+                sound_id,
+                sound_volume,
+            } => {
+                for instance in self
+                    .sound_instances
+                    .values_mut()
+                    .filter(|instance| instance.sound_id == sound_id)
+                {
+                    instance.update_sound_volume(sound_volume);
+                }
+            }
+            AudioCommand::UpdatePan { sound_id, pan } => {
+                for instance in self
+                    .sound_instances
+                    .values()
+                    .filter(|instance| instance.sound_id == sound_id)
+                {
+                    instance.update_pan(pan);
+                }
+            }
+            AudioCommand::UpdateDeviceVolumes => {
+                let routes = self.backend.routes();
+                for instance in self.sound_instances.values_mut() {
+                    instance.update_device_volumes(&routes);
+                }
+                if let (Some(input), Some(route)) = (
+                    &self.input,
+                    routes.iter().find(|route| route.name == VIRTUAL_ROUTE),
+                ) {
+                    input.control.set_volume(route.effective_volume());
+                    input.control.set_channel_mode(route.channel_mode);
+                }
+                info!(
+                    "Updated route volumes and channel modes for all playing sounds ({} routes)",
+                    routes.len()
+                );
+            }
+            AudioCommand::AttachInput { input } => {
+                self.attach_input(input);
+            }
+            AudioCommand::DetachInput => {
+                self.detach_input();
+            }
+            AudioCommand::UpdateEffects { sound_id, effects } => {
+                for instance in self
+                    .sound_instances
+                    .values()
+                    .filter(|instance| instance.sound_id == sound_id)
+                {
+                    instance
+                        .effects
+                        .set_chain(with_jitter(instance.jitter_rate, effects.clone()));
+                }
+                info!(
+                    "Updated effects for sound {} ({} effects)",
+                    sound_id,
+                    effects.len()
+                );
+            }
+            AudioCommand::ResetDevice { device_name } => {
+                reset_device(
+                    &device_name,
+                    &mut self.mixers,
+                    &mut self.sound_instances,
+                    &self.playing,
+                );
+                // went down with the stream, input capture is restarted once the device is back
+                if self
+                    .input
+                    .as_ref()
+                    .is_some_and(|input| input.device_name == device_name)
+                {
+                    self.input = None;
+                }
+            }
+            AudioCommand::Shutdown => {
+                info!("Audio thread received shutdown signal");
+                for (_id, instance) in self.sound_instances.drain() {
+                    instance.stop();
+                }
+                self.detach_input();
+                self.playing.lock().expect("Lock poisoned").clear();
+                return false;
+            }
+        }
+        true
     }
 }
 
 fn audio_thread_worker(
+    backend: Arc<dyn AudioBackend>,
     command_rx: Receiver<AudioCommand>,
-    playing_thread: Arc<Mutex<HashMap<String, (Instant, f32, f32)>>>,
+    playing_thread: Arc<Mutex<HashMap<String, PlayingSound>>>,
+    voice_limit: Arc<Mutex<VoiceLimit>>,
+    progress_interval_ms: Arc<AtomicU32>,
 ) {
-    let mut sound_instances: HashMap<String, SoundInstance> = HashMap::new();
+    let mut core = EngineCore::with_playing(backend, playing_thread.clone());
     let tick = Duration::from_millis(50);
+    let mut last_progress = Instant::now();
 
     loop {
         match command_rx.recv_timeout(tick) {
             Ok(cmd) => {
-                match cmd {
-                    AudioCommand::Play { file_path, sound_id, start_position, sound_volume, local_only } => {
-                        handle_play_command(
-                            &file_path,
-                            &sound_id,
-                            start_position,
-                            sound_volume,
-                            local_only,
-                            &mut sound_instances,
-                            &playing_thread,
-                        );
-                    }
-                    AudioCommand::Stop { sound_id } => {
-                        if let Some(instance) = sound_instances.remove(&sound_id) {
-                            instance.stop();
-                        }
-                        playing_thread.lock().expect("Lock poisoned").remove(&sound_id);
-                    }
-                    AudioCommand::StopAll => {
-                        for (_id, instance) in sound_instances.drain() {
-                            instance.stop();
-                        }
-                        playing_thread.lock().expect("Lock poisoned").clear();
-                    }
-                    AudioCommand::UpdateSoundVolume { sound_id, sound_volume } => {
-                        if let Some(instance) = sound_instances.get_mut(&sound_id) {
-                            instance.update_sound_volume(sound_volume);
-                        }
-                    }
-                    AudioCommand::UpdateDeviceVolumes => {
-                        let manager = get_audio_manager();
-                        let virtual_volume = manager.get_virtual_volume();
-                        let output_volume = manager.get_output_volume();
-                        for instance in sound_instances.values_mut() {
-                            instance.update_device_volumes(virtual_volume, output_volume);
-                        }
-                        info!("Updated device volumes for all playing sounds (virtual: {}, output: {})", virtual_volume, output_volume);
-                    }
-                    AudioCommand::Shutdown => {
-                        info!("Audio thread received shutdown signal");
-                        for (_id, instance) in sound_instances.drain() {
-                            instance.stop();
-                        }
-                        playing_thread.lock().expect("Lock poisoned").clear();
-                        break;
-                    }
+                if !core.handle_command(cmd, *voice_limit.lock().expect("Lock poisoned")) {
+                    break;
                 }
             }
             Err(RecvTimeoutError::Timeout) => {
@@ -311,62 +1194,119 @@ fn audio_thread_worker(
                 break;
             }
         }
-        cleanup_finished_sounds(&mut sound_instances, &playing_thread);
+        core.cleanup_finished();
+
+        let interval = progress_interval_ms.load(Ordering::Relaxed);
+        if interval > 0 && last_progress.elapsed() >= Duration::from_millis(interval as u64) {
+            last_progress = Instant::now();
+            let progress: Vec<PlayingSoundInfo> = playing_thread
+                .lock()
+                .expect("Lock poisoned")
+                .iter()
+                .map(|(id, playing)| playing.info(id))
+                .collect();
+            if !progress.is_empty() {
+                emit_event("sound-progress", progress);
+            }
+        }
     }
 }
 
 pub struct AudioEngine {
     command_tx: Sender<AudioCommand>,
-    playing: Arc<Mutex<HashMap<String, (Instant, f32, f32)>>>,
+    playing: Arc<Mutex<HashMap<String, PlayingSound>>>,
+    voice_limit: Arc<Mutex<VoiceLimit>>,
+    progress_interval_ms: Arc<AtomicU32>,
 }
 
 impl AudioEngine {
     pub fn new() -> Self {
+        Self::with_backend(Arc::new(CpalBackend))
+    }
+
+    // an engine playing through something other than the sound cards, like a NullBackend
+    pub fn with_backend(backend: Arc<dyn AudioBackend>) -> Self {
         let (command_tx, command_rx): (Sender<AudioCommand>, Receiver<AudioCommand>) = mpsc::channel();
         let playing = Arc::new(Mutex::new(HashMap::new()));
         let playing_thread = playing.clone();
+        let voice_limit = Arc::new(Mutex::new(VoiceLimit::default()));
+        let voice_limit_thread = voice_limit.clone();
+        let progress_interval_ms = Arc::new(AtomicU32::new(DEFAULT_PROGRESS_INTERVAL_MS));
+        let progress_interval_thread = progress_interval_ms.clone();
         
         thread::spawn(move || {
-            audio_thread_worker(command_rx, playing_thread);
+            audio_thread_worker(backend, command_rx, playing_thread, voice_limit_thread, progress_interval_thread);
         });
         
-        Self { command_tx, playing }
+        Self { command_tx, playing, voice_limit, progress_interval_ms }
+    }
+
+    // how often sound-progress is emitted, 0 turns it off. the audio thread ticks every 50ms so that's the floor
+    pub fn set_progress_interval(&self, interval_ms: u32) {
+        self.progress_interval_ms.store(interval_ms, Ordering::Relaxed);
+    }
+
+    pub fn get_progress_interval(&self) -> u32 {
+        self.progress_interval_ms.load(Ordering::Relaxed)
+    }
+
+    pub fn get_voice_limit(&self) -> VoiceLimit {
+        *self.voice_limit.lock().expect("Lock poisoned")
+    }
+
+    // applies to the next sound that starts, voices already playing are left alone
+    pub fn set_voice_limit(&self, voice_limit: VoiceLimit) {
+        *self.voice_limit.lock().expect("Lock poisoned") = voice_limit;
+        info!("Voice limit set to {} ({})", voice_limit.max_voices, voice_limit.policy.as_str());
     }
 
     pub fn send_command(&self, cmd: AudioCommand) {
         let _ = self.command_tx.send(cmd);
     }
 
+    // sound ids, a sound playing several overlapping voices is listed once
     pub fn get_playing_sounds(&self) -> Vec<String> {
-        // duration stored here is already the remaining duration from the start position
+        let playing = self.playing.lock().expect("Lock poisoned");
+        let mut sound_ids: Vec<String> = Vec::new();
+        for playing_sound in playing.values() {
+            if !sound_ids.contains(&playing_sound.sound_id) {
+                sound_ids.push(playing_sound.sound_id.clone());
+            }
+        }
+        sound_ids
+    }
+
+    pub fn get_playing_sounds_info(&self) -> Vec<PlayingSoundInfo> {
         self
             .playing
             .lock()
             .expect("Lock poisoned")
             .iter()
-            .filter_map(|(id, (start_time, remaining_duration, _start_position))| {
-                let elapsed = start_time.elapsed().as_secs_f32();
-                if *remaining_duration == 0.0 || elapsed < *remaining_duration {
-                    Some(id.clone())
-                } else {
-                    None
-                }
-            })
+            .map(|(id, playing)| playing.info(id))
+            .collect()
+    }
+
+    pub fn get_paused_sounds(&self) -> Vec<String> {
+        self
+            .playing
+            .lock()
+            .expect("Lock poisoned")
+            .values()
+            .filter(|playing| playing.paused)
+            .map(|playing| playing.sound_id.clone())
             .collect()
     }
 
+    // position of the most recently started voice of the sound
     pub fn get_playback_position(&self, sound_id: &str) -> Option<f32> {
-        let playing = self.playing.lock().expect("Lock poisoned");
-        if let Some((start_time, remaining_duration, start_position)) = playing.get(sound_id) {
-            let elapsed = start_time.elapsed().as_secs_f32();
-            if *remaining_duration == 0.0 || elapsed < *remaining_duration {
-                Some(*start_position + elapsed)
-            } else {
-                None
-            }
-        } else {
-            None
-        }
+        self
+            .playing
+            .lock()
+            .expect("Lock poisoned")
+            .values()
+            .filter(|playing| playing.sound_id == sound_id)
+            .max_by_key(|playing| playing.started)
+            .map(|playing| playing.position())
     }
 }
 
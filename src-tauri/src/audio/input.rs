@@ -0,0 +1,236 @@
+use anyhow::Result;
+use rodio::Source;
+use std::{
+    collections::VecDeque,
+    f64::consts::PI,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use crate::audio::{DuckTarget, Ducker, DuckingControl, MicProcessingSettings, MicProcessor, SeekableSource};
+
+// frames moved out of the capture buffer per lock
+const CHUNK_FRAMES: usize = 256;
+// interpolation kernel length in input frames, half on each side of the output position
+const TAPS: usize = 16;
+// kernel table resolution between two input frames
+const PHASES: usize = 256;
+// how much audio we aim to keep queued between the capture callback and the mix
+const TARGET_LATENCY_MS: u32 = 30;
+// a queue this far over target (a stall, a device hiccup) is cut back instead of slowly played off
+const MAX_LATENCY_MS: u32 = 200;
+// most the playback rate is nudged to follow clock drift, 0.5% is about 9 cents
+const MAX_RATE_CORRECTION: f64 = 0.005;
+// smoothing of the fill level per refill, so capture bursts don't wobble the rate
+const FILL_SMOOTHING: f64 = 0.02;
+// opening or closing the mic from a hotkey ramps over this long so it doesn't click
+const SWITCH_MS: u32 = 5;
+
+// what the capture thread hands the engine: the captured samples and the format they're in
+#[derive(Debug, Clone)]
+pub struct MicInput {
+    pub buffer: Arc<Mutex<VecDeque<f32>>>,
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub volume: Arc<Mutex<f32>>,
+    pub processing: Arc<MicProcessingSettings>,
+    pub ducking: Arc<DuckingControl>,
+    // cleared while push-to-talk isn't held, push-to-mute is or the mic is toggled off
+    pub open: Arc<AtomicBool>,
+}
+
+// blackman-windowed sinc, one row of TAPS weights per phase. rows are normalised so a constant
+// signal passes at unity whatever the phase
+fn build_kernel(cutoff: f64) -> Vec<f32> {
+    let half = (TAPS / 2) as f64;
+    let mut table = Vec::with_capacity((PHASES + 1) * TAPS);
+    for phase in 0..=PHASES {
+        let frac = phase as f64 / PHASES as f64;
+        let row: Vec<f64> = (0..TAPS)
+            .map(|tap| {
+                // distance from the output position to this tap's input frame
+                let x = tap as f64 - (half - 1.0) - frac;
+                let sinc = if x.abs() < 1e-9 { 1.0 } else { (PI * cutoff * x).sin() / (PI * cutoff * x) };
+                let w = (x / half).clamp(-1.0, 1.0);
+                let window = 0.42 + 0.5 * (PI * w).cos() + 0.08 * (2.0 * PI * w).cos();
+                cutoff * sinc * window
+            })
+            .collect();
+        let sum: f64 = row.iter().sum();
+        table.extend(row.iter().map(|weight| (weight / sum) as f32));
+    }
+    table
+}
+
+// the mic as a voice in the virtual device's mix, resampled from the capture rate to the mixer's.
+// the two run on different clocks, so the rate is nudged to hold the queue near its target instead
+// of letting it grow or run dry. it never ends on its own, and plays silence whenever the capture
+// hasn't delivered anything
+pub struct MicSource {
+    input: MicInput,
+    output_rate: u32,
+    kernel: Vec<f32>,
+    // input frames being interpolated over, interleaved. the output position sits between
+    // frames TAPS / 2 - 1 and TAPS / 2
+    window: VecDeque<f32>,
+    position: f64,
+    // input frames per output frame before drift correction
+    base_step: f64,
+    step: f64,
+    average_fill: f64,
+    target_fill: f64,
+    max_fill: usize,
+    processor: MicProcessor,
+    ducker: Ducker,
+    open_gain: f32,
+    switch_step: f32,
+    volume: f32,
+    frame: Vec<f32>,
+    frame_pos: usize,
+}
+
+impl MicSource {
+    pub fn new(input: MicInput, output_rate: u32) -> Self {
+        let channels = input.channels.max(1) as usize;
+        let input_rate = input.sample_rate.max(1);
+        let output_rate = output_rate.max(1);
+        let base_step = input_rate as f64 / output_rate as f64;
+        // keep a little below nyquist of whichever side is lower
+        let cutoff = (output_rate as f64 / input_rate as f64).min(1.0) * 0.95;
+        let target_fill = (input_rate * TARGET_LATENCY_MS / 1000) as f64;
+        let volume = *input.volume.lock().unwrap();
+        Self {
+            output_rate,
+            kernel: build_kernel(cutoff),
+            window: VecDeque::with_capacity((TAPS + CHUNK_FRAMES) * channels),
+            position: 0.0,
+            base_step,
+            step: base_step,
+            average_fill: target_fill,
+            target_fill,
+            max_fill: (input_rate * MAX_LATENCY_MS / 1000) as usize,
+            processor: MicProcessor::new(input.processing.clone(), output_rate),
+            ducker: Ducker::new(input.ducking.clone(), DuckTarget::Mic, output_rate),
+            open_gain: if input.open.load(Ordering::SeqCst) { 1.0 } else { 0.0 },
+            switch_step: 1.0 / (output_rate * SWITCH_MS / 1000).max(1) as f32,
+            volume,
+            frame: vec![0.0; channels],
+            frame_pos: channels,
+            input,
+        }
+    }
+
+    fn input_channels(&self) -> usize {
+        self.input.channels.max(1) as usize
+    }
+
+    fn window_frames(&self) -> usize {
+        self.window.len() / self.input_channels()
+    }
+
+    // tops the window up from the capture buffer and retunes the rate from how full it is
+    fn refill(&mut self) {
+        let channels = self.input_channels();
+        self.volume = *self.input.volume.lock().unwrap();
+        let mut buffer = self.input.buffer.lock().unwrap();
+        let mut queued = buffer.len() / channels;
+
+        if queued > self.max_fill {
+            let target = self.target_fill as usize;
+            buffer.drain(..(queued - target) * channels);
+            queued = target;
+            self.average_fill = self.target_fill;
+        }
+
+        let fill = (queued + self.window_frames()) as f64;
+        self.average_fill += (fill - self.average_fill) * FILL_SMOOTHING;
+        let error = (self.average_fill - self.target_fill) / self.target_fill.max(1.0);
+        // more queued than we want means we're behind, so read a touch faster
+        let correction = (error * MAX_RATE_CORRECTION).clamp(-MAX_RATE_CORRECTION, MAX_RATE_CORRECTION);
+        self.step = self.base_step * (1.0 + correction);
+
+        let take = queued.min(CHUNK_FRAMES) * channels;
+        self.window.extend(buffer.drain(..take));
+    }
+
+    // interpolates the next output frame, or leaves silence if the capture has fallen behind
+    fn render_frame(&mut self) {
+        let channels = self.input_channels();
+        if self.window_frames() < TAPS {
+            self.refill();
+        }
+        if self.window_frames() < TAPS {
+            self.frame.iter_mut().for_each(|sample| *sample = 0.0);
+            return;
+        }
+
+        let scaled = self.position * PHASES as f64;
+        let phase = (scaled as usize).min(PHASES - 1);
+        let blend = (scaled - phase as f64) as f32;
+        let row = &self.kernel[phase * TAPS..(phase + 1) * TAPS];
+        let next_row = &self.kernel[(phase + 1) * TAPS..(phase + 2) * TAPS];
+        for (channel, out) in self.frame.iter_mut().enumerate() {
+            let mut sum = 0.0;
+            for tap in 0..TAPS {
+                let weight = row[tap] + (next_row[tap] - row[tap]) * blend;
+                sum += weight * self.window[tap * channels + channel];
+            }
+            *out = sum;
+        }
+        // the gate and detectors see the mic's own level, the input volume and ducking come after them
+        self.processor.process(&mut self.frame);
+        let open_target = if self.input.open.load(Ordering::Relaxed) { 1.0 } else { 0.0 };
+        self.open_gain += (open_target - self.open_gain).clamp(-self.switch_step, self.switch_step);
+        let volume = self.volume * self.ducker.next_gain() * self.open_gain;
+        self.frame.iter_mut().for_each(|sample| *sample *= volume);
+
+        self.position += self.step;
+        let advance = self.position as usize;
+        if advance > 0 {
+            self.position -= advance as f64;
+            let consumed = (advance * channels).min(self.window.len());
+            self.window.drain(..consumed);
+        }
+    }
+}
+
+impl Iterator for MicSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.frame_pos >= self.frame.len() {
+            self.render_frame();
+            self.frame_pos = 0;
+        }
+        let sample = self.frame[self.frame_pos];
+        self.frame_pos += 1;
+        Some(sample)
+    }
+}
+
+impl Source for MicSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels.max(1)
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.output_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+// live input has nowhere to seek to
+impl SeekableSource for MicSource {
+    fn seek(&mut self, _position: f32) -> Result<()> {
+        Ok(())
+    }
+}
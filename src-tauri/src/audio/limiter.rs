@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+};
+
+pub const DEFAULT_CEILING_DB: f32 = -1.0;
+const LOOKAHEAD_MS: u32 = 5;
+const RELEASE_MS: f32 = 100.0;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct LimiterConfig {
+    pub enabled: bool,
+    pub ceiling_db: f32,
+}
+
+// shared between the manager (commands write it) and the device callback (reads it every frame)
+pub struct LimiterSettings {
+    enabled: AtomicBool,
+    ceiling_db: AtomicU32,
+}
+
+impl LimiterSettings {
+    pub fn new(enabled: bool, ceiling_db: f32) -> Self {
+        Self {
+            enabled: AtomicBool::new(enabled),
+            ceiling_db: AtomicU32::new(ceiling_db.to_bits()),
+        }
+    }
+
+    pub fn config(&self) -> LimiterConfig {
+        LimiterConfig {
+            enabled: self.enabled.load(Ordering::Relaxed),
+            ceiling_db: f32::from_bits(self.ceiling_db.load(Ordering::Relaxed)),
+        }
+    }
+
+    pub fn set_config(&self, config: LimiterConfig) {
+        self.enabled.store(config.enabled, Ordering::Relaxed);
+        self.ceiling_db.store(config.ceiling_db.clamp(-24.0, 0.0).to_bits(), Ordering::Relaxed);
+    }
+}
+
+impl Default for LimiterSettings {
+    fn default() -> Self {
+        Self::new(true, DEFAULT_CEILING_DB)
+    }
+}
+
+// look-ahead peak limiter, frames come out LOOKAHEAD_MS late so the gain is already down when a peak arrives
+pub struct Limiter {
+    channels: usize,
+    lookahead_frames: u64,
+    delay: VecDeque<f32>,
+    // (frame index, peak) kept in decreasing peak order, the front is the loudest frame in the window
+    peaks: VecDeque<(u64, f32)>,
+    frame_index: u64,
+    gain: f32,
+    attack_coeff: f32,
+    release_coeff: f32,
+}
+
+impl Limiter {
+    pub fn new(channels: u16, sample_rate: u32) -> Self {
+        let channels = channels.max(1) as usize;
+        let lookahead_frames = (sample_rate as u64 * LOOKAHEAD_MS as u64 / 1000).max(1);
+        let release_frames = sample_rate as f32 * RELEASE_MS / 1000.0;
+        Self {
+            channels,
+            lookahead_frames,
+            delay: VecDeque::from(vec![0.0; lookahead_frames as usize * channels]),
+            peaks: VecDeque::new(),
+            frame_index: 0,
+            gain: 1.0,
+            attack_coeff: 1.0 - (-4.0 / lookahead_frames as f32).exp(),
+            release_coeff: 1.0 - (-1.0 / release_frames.max(1.0)).exp(),
+        }
+    }
+
+    // takes one interleaved frame and replaces it with the limited frame from LOOKAHEAD_MS ago
+    pub fn process(&mut self, frame: &mut [f32], settings: &LimiterSettings) {
+        let config = settings.config();
+        let ceiling = db_to_gain(config.ceiling_db);
+
+        let peak = frame.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        while self.peaks.back().is_some_and(|(_, p)| *p <= peak) {
+            self.peaks.pop_back();
+        }
+        self.peaks.push_back((self.frame_index, peak));
+        while self.peaks.front().is_some_and(|(index, _)| index + self.lookahead_frames <= self.frame_index) {
+            self.peaks.pop_front();
+        }
+        self.frame_index += 1;
+
+        let window_peak = self.peaks.front().map(|(_, p)| *p).unwrap_or(0.0);
+        let target = if config.enabled && window_peak > ceiling { ceiling / window_peak } else { 1.0 };
+        let coeff = if target < self.gain { self.attack_coeff } else { self.release_coeff };
+        self.gain += (target - self.gain) * coeff;
+
+        for sample in frame.iter_mut().take(self.channels) {
+            self.delay.push_back(*sample);
+            let delayed = self.delay.pop_front().unwrap_or(0.0) * self.gain;
+            *sample = if config.enabled { delayed.clamp(-ceiling, ceiling) } else { delayed };
+        }
+    }
+}
+
+pub fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
@@ -0,0 +1,251 @@
+use anyhow::Result;
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use crate::audio::SymphoniaAudioSource;
+
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const RELATIVE_GATE_LU: f64 = -10.0;
+const MAX_NORMALIZATION_BOOST_DB: f32 = 12.0;
+const MAX_NORMALIZATION_CUT_DB: f32 = -24.0;
+const TRUE_PEAK_OVERSAMPLING: usize = 4;
+const TRUE_PEAK_TAPS: usize = 12;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct LoudnessAnalysis {
+    // None for clips that are silent below the absolute gate
+    pub integrated_lufs: Option<f32>,
+    pub true_peak_db: f32,
+}
+
+// EBU R128 / BS.1770 integrated loudness and true peak of a whole file
+pub fn analyze_loudness(file_path: &str) -> Result<LoudnessAnalysis> {
+    let source = SymphoniaAudioSource::new(file_path, 0.0)?;
+    let channels = source.channels().max(1) as usize;
+    let sample_rate = source.sample_rate().max(1);
+
+    let mut filters: Vec<KWeighting> = (0..channels).map(|_| KWeighting::new(sample_rate as f64)).collect();
+    let mut peaks: Vec<TruePeak> = (0..channels).map(|_| TruePeak::new()).collect();
+    let weights = channel_weights(channels);
+
+    // 100ms sub-blocks, the 400ms gating blocks overlap by 75% and are built from four of them
+    let sub_block_frames = (sample_rate as usize / 10).max(1);
+    let mut sub_block_power: Vec<f64> = Vec::new();
+    let mut sums = vec![0.0f64; channels];
+    let mut frames_in_sub_block = 0;
+    let mut channel = 0;
+
+    for sample in source {
+        let filtered = filters[channel].process(sample as f64);
+        sums[channel] += filtered * filtered;
+        peaks[channel].push(sample);
+        channel += 1;
+        if channel == channels {
+            channel = 0;
+            frames_in_sub_block += 1;
+            if frames_in_sub_block == sub_block_frames {
+                sub_block_power.push(weighted_power(&sums, &weights, sub_block_frames));
+                sums.iter_mut().for_each(|sum| *sum = 0.0);
+                frames_in_sub_block = 0;
+            }
+        }
+    }
+
+    let mut blocks: Vec<f64> = sub_block_power
+        .windows(4)
+        .map(|window| window.iter().sum::<f64>() / 4.0)
+        .collect();
+    // clips shorter than one gating block are measured as a single block
+    if blocks.is_empty() {
+        let frames = sub_block_power.len() * sub_block_frames + frames_in_sub_block;
+        if frames > 0 {
+            let full: f64 = sub_block_power.iter().map(|p| p * sub_block_frames as f64).sum();
+            blocks.push(full / frames as f64 + weighted_power(&sums, &weights, frames));
+        }
+    }
+
+    let true_peak = peaks.iter_mut().map(|peak| peak.finish()).fold(0.0f32, f32::max);
+    Ok(LoudnessAnalysis {
+        integrated_lufs: gated_loudness(&blocks).map(|lufs| lufs as f32),
+        true_peak_db: if true_peak > 0.0 { 20.0 * true_peak.log10() } else { f32::NEG_INFINITY },
+    })
+}
+
+// gain in dB that brings a sound to the target, clamped so quiet clips don't get boosted into noise
+pub fn normalization_gain_db(integrated_lufs: f32, target_lufs: f32) -> f32 {
+    (target_lufs - integrated_lufs).clamp(MAX_NORMALIZATION_CUT_DB, MAX_NORMALIZATION_BOOST_DB)
+}
+
+fn weighted_power(sums: &[f64], weights: &[f64], frames: usize) -> f64 {
+    sums.iter()
+        .zip(weights)
+        .map(|(sum, weight)| weight * sum / frames.max(1) as f64)
+        .sum()
+}
+
+fn power_to_lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+fn gated_loudness(blocks: &[f64]) -> Option<f64> {
+    let above_absolute: Vec<f64> = blocks
+        .iter()
+        .copied()
+        .filter(|power| *power > 0.0 && power_to_lufs(*power) > ABSOLUTE_GATE_LUFS)
+        .collect();
+    if above_absolute.is_empty() {
+        return None;
+    }
+    let relative_gate = power_to_lufs(above_absolute.iter().sum::<f64>() / above_absolute.len() as f64) + RELATIVE_GATE_LU;
+    let gated: Vec<f64> = above_absolute
+        .into_iter()
+        .filter(|power| power_to_lufs(*power) > relative_gate)
+        .collect();
+    if gated.is_empty() {
+        return None;
+    }
+    Some(power_to_lufs(gated.iter().sum::<f64>() / gated.len() as f64))
+}
+
+// BS.1770 weights, surround channels of a 5.1 file count more and the LFE not at all
+fn channel_weights(channels: usize) -> Vec<f64> {
+    if channels == 6 {
+        vec![1.0, 1.0, 1.0, 0.0, 1.41, 1.41]
+    } else {
+        vec![1.0; channels]
+    }
+}
+
+// transposed direct form II, a[0] is assumed to be 1
+pub(crate) struct Biquad {
+    b: [f64; 3],
+    a: [f64; 3],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    // normalizes by a0, which the cookbook formulas leave in
+    pub(crate) fn new(b: [f64; 3], a: [f64; 3]) -> Self {
+        let a0 = a[0];
+        Self {
+            b: [b[0] / a0, b[1] / a0, b[2] / a0],
+            a: [1.0, a[1] / a0, a[2] / a0],
+            z: [0.0; 2],
+        }
+    }
+
+    // swaps coefficients but keeps the filter state, so a live change doesn't click
+    pub(crate) fn set_coefficients(&mut self, other: &Biquad) {
+        self.b = other.b;
+        self.a = other.a;
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.z = [0.0; 2];
+    }
+
+    pub(crate) fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[1] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[2] * y;
+        y
+    }
+}
+
+// the two-stage K-weighting filter, coefficients derived for any sample rate
+struct KWeighting {
+    shelf: Biquad,
+    high_pass: Biquad,
+}
+
+impl KWeighting {
+    fn new(sample_rate: f64) -> Self {
+        let f0 = 1681.974450955533;
+        let gain_db = 3.999843853973347;
+        let q = 0.7071752369554196;
+        let k = (PI * f0 / sample_rate).tan();
+        let vh = 10f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad {
+            b: [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+            a: [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            z: [0.0; 2],
+        };
+
+        let f0 = 38.13547087602444;
+        let q = 0.5003270373238773;
+        let k = (PI * f0 / sample_rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let high_pass = Biquad {
+            b: [1.0, -2.0, 1.0],
+            a: [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            z: [0.0; 2],
+        };
+
+        Self { shelf, high_pass }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        self.high_pass.process(self.shelf.process(x))
+    }
+}
+
+// 4x oversampled peak through a windowed-sinc interpolator, catches the inter-sample peaks a DAC would produce
+struct TruePeak {
+    history: Vec<f32>,
+    pos: usize,
+    phases: Vec<Vec<f32>>,
+    peak: f32,
+}
+
+impl TruePeak {
+    fn new() -> Self {
+        let len = TRUE_PEAK_TAPS * TRUE_PEAK_OVERSAMPLING;
+        let center = (len - 1) as f64 / 2.0;
+        let taps: Vec<f64> = (0..len)
+            .map(|i| {
+                let x = (i as f64 - center) / TRUE_PEAK_OVERSAMPLING as f64;
+                let sinc = if x == 0.0 { 1.0 } else { (PI * x).sin() / (PI * x) };
+                let window = 0.5 - 0.5 * (2.0 * PI * i as f64 / (len - 1) as f64).cos();
+                sinc * window
+            })
+            .collect();
+        let phases = (0..TRUE_PEAK_OVERSAMPLING)
+            .map(|phase| {
+                (0..TRUE_PEAK_TAPS)
+                    .map(|tap| taps[tap * TRUE_PEAK_OVERSAMPLING + phase] as f32)
+                    .collect()
+            })
+            .collect();
+        Self {
+            history: vec![0.0; TRUE_PEAK_TAPS],
+            pos: 0,
+            phases,
+            peak: 0.0,
+        }
+    }
+
+    fn push(&mut self, sample: f32) {
+        self.history[self.pos] = sample;
+        self.pos = (self.pos + 1) % TRUE_PEAK_TAPS;
+        self.peak = self.peak.max(sample.abs());
+        for phase in &self.phases {
+            let mut acc = 0.0;
+            for (tap, coeff) in phase.iter().enumerate() {
+                // newest sample first
+                let index = (self.pos + TRUE_PEAK_TAPS - 1 - tap) % TRUE_PEAK_TAPS;
+                acc += coeff * self.history[index];
+            }
+            self.peak = self.peak.max(acc.abs());
+        }
+    }
+
+    fn finish(&mut self) -> f32 {
+        // flush the interpolator so the last samples are looked at too
+        for _ in 0..TRUE_PEAK_TAPS {
+            self.push(0.0);
+        }
+        self.peak
+    }
+}
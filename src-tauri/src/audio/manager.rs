@@ -1,26 +1,99 @@
 use anyhow::{Context, Result};
 use cpal::{
     traits::{DeviceTrait, HostTrait, StreamTrait},
-    Device, Host, SupportedStreamConfigRange, Sample, SampleFormat, StreamConfig,
+    Device, Host, Sample, SampleFormat, StreamConfig,
 };
+use serde::{Deserialize, Serialize};
 use std::{
     collections::{HashMap, VecDeque},
     sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}},
 };
 use tracing::{info, warn};
 use once_cell::sync::OnceCell;
+use crate::audio::{AudioCommand, ChannelMode, DuckingControl, LimiterSettings, MicInput, MicProcessingSettings, emit_event, get_audio_engine};
+
+// the two routes every install has, they can be re-pointed but not removed
+pub const VIRTUAL_ROUTE: &str = "virtual";
+pub const OUTPUT_ROUTE: &str = "output";
+pub const INPUT_BUS: &str = "input";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputRoute {
+    pub name: String,
+    // None leaves the route unassigned, it's skipped at playback
+    pub device: Option<String>,
+    pub volume: f32,
+    #[serde(default)]
+    pub muted: bool,
+    #[serde(default)]
+    pub channel_mode: ChannelMode,
+}
+
+impl OutputRoute {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            device: None,
+            volume: 1.0,
+            muted: false,
+            channel_mode: ChannelMode::default(),
+        }
+    }
+
+    pub fn effective_volume(&self) -> f32 {
+        if self.muted { 0.0 } else { self.volume }
+    }
+}
+
+// a device saved in settings that wasn't there at startup
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingDevice {
+    // route name, or "input" for the capture device
+    pub bus: String,
+    pub device: String,
+    pub fallback: Option<String>,
+}
+
+// what the mic hotkeys have asked for, passthrough is open only when they all agree
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct MicState {
+    // a push-to-talk key is bound, so the mic stays closed unless it's held
+    pub push_to_talk: bool,
+    pub talking: bool,
+    pub push_muted: bool,
+    pub toggled_off: bool,
+    pub open: bool,
+}
+
+impl MicState {
+    fn is_open(&self) -> bool {
+        !self.toggled_off && !self.push_muted && (!self.push_to_talk || self.talking)
+    }
+}
 
 pub struct AudioManager {
     host: Host,
-    virtual_device: Arc<Mutex<Option<Device>>>,
-    output_device: Arc<Mutex<Option<Device>>>,
+    routes: Arc<Mutex<Vec<OutputRoute>>>,
     input_device: Arc<Mutex<Option<Device>>>,
-    virtual_volume: Arc<Mutex<f32>>,
-    output_volume: Arc<Mutex<f32>>,
     input_volume: Arc<Mutex<f32>>,
+    virtual_limiter: Arc<LimiterSettings>,
+    output_limiter: Arc<LimiterSettings>,
+    mic_processing: Arc<MicProcessingSettings>,
+    ducking: Arc<DuckingControl>,
+    mic_state: Mutex<MicState>,
+    mic_open: Arc<AtomicBool>,
     input_capture: Arc<Mutex<Option<InputCaptureControl>>>,
     playback_positions: Arc<Mutex<HashMap<String, f32>>>,
     playback_start_times: Arc<Mutex<HashMap<String, std::time::Instant>>>,
+    missing_devices: Mutex<Vec<MissingDevice>>,
+}
+
+// a device plays each sound once, so it can only back one route
+fn check_device_free(routes: &[OutputRoute], name: &str, device_name: &str) -> Result<()> {
+    match routes.iter().find(|r| r.name != name && r.device.as_deref() == Some(device_name)) {
+        Some(other) => Err(anyhow::anyhow!("{} is already used by the {} route", device_name, other.name)),
+        None => Ok(()),
+    }
 }
 
 impl AudioManager {
@@ -28,15 +101,19 @@ impl AudioManager {
         let host = cpal::default_host();
         let manager = Self {
             host,
-            virtual_device: Arc::new(Mutex::new(None)),
-            output_device: Arc::new(Mutex::new(None)),
+            routes: Arc::new(Mutex::new(vec![OutputRoute::new(VIRTUAL_ROUTE), OutputRoute::new(OUTPUT_ROUTE)])),
             input_device: Arc::new(Mutex::new(None)),
-            virtual_volume: Arc::new(Mutex::new(1.0)),
-            output_volume: Arc::new(Mutex::new(1.0)),
             input_volume: Arc::new(Mutex::new(1.0)),
+            virtual_limiter: Arc::new(LimiterSettings::default()),
+            output_limiter: Arc::new(LimiterSettings::default()),
+            mic_processing: Arc::new(MicProcessingSettings::default()),
+            ducking: Arc::new(DuckingControl::default()),
+            mic_state: Mutex::new(MicState { open: true, ..Default::default() }),
+            mic_open: Arc::new(AtomicBool::new(true)),
             input_capture: Arc::new(Mutex::new(None)),
             playback_positions: Arc::new(Mutex::new(HashMap::new())),
             playback_start_times: Arc::new(Mutex::new(HashMap::new())),
+            missing_devices: Mutex::new(Vec::new()),
         };
 
         info!("Initializing audio system...");
@@ -51,8 +128,11 @@ impl AudioManager {
 
         if let Some(default_device) = manager.host.default_output_device() {
             let device_name = default_device.name().unwrap_or_default();
-            manager.set_output_device(&device_name)?;
-            info!("Set default output device: {}", device_name);
+            // the default output can be the virtual cable itself, which the virtual route already holds
+            match manager.set_output_device(&device_name) {
+                Ok(()) => info!("Set default output device: {}", device_name),
+                Err(e) => warn!("Leaving the output route unassigned: {}", e),
+            }
         } else {
             warn!("No default output device found");
         }
@@ -66,8 +146,9 @@ impl AudioManager {
         }
 
         info!("Audio system initialization complete");
-        info!("Virtual device configured: {}", manager.virtual_device.lock().unwrap().is_some());
-        info!("Output device configured: {}", manager.output_device.lock().unwrap().is_some());
+        for route in manager.get_routes() {
+            info!("Route {} configured: {}", route.name, route.device.is_some());
+        }
         info!("Input device configured: {}", manager.input_device.lock().unwrap().is_some());
 
         Ok(manager)
@@ -89,16 +170,115 @@ impl AudioManager {
         None
     }
     
-    pub fn set_virtual_device(&self, device_name: &str) -> Result<()> {
-        if let Ok(mut output_devices) = self.host.output_devices() {
-            if let Some(device) = output_devices.find(|d| d.name().unwrap_or_default() == device_name) {
-                *self.virtual_device.lock().unwrap() = Some(device);
-                info!("Set virtual device to: {} (output device)", device_name);
-                return Ok(());
+    pub fn find_output_device(&self, device_name: &str) -> Option<Device> {
+        self.host
+            .output_devices()
+            .ok()?
+            .find(|d| d.name().unwrap_or_default() == device_name)
+    }
+
+    pub fn get_routes(&self) -> Vec<OutputRoute> {
+        self.routes.lock().unwrap().clone()
+    }
+
+    pub fn get_route(&self, name: &str) -> Option<OutputRoute> {
+        self.routes.lock().unwrap().iter().find(|r| r.name == name).cloned()
+    }
+
+    // adds the route or replaces the one with the same name
+    pub fn set_route(&self, mut route: OutputRoute) -> Result<()> {
+        route.name = route.name.trim().to_string();
+        if route.name.is_empty() {
+            return Err(anyhow::anyhow!("Route name cannot be empty"));
+        }
+        if let Some(device_name) = &route.device {
+            if self.find_output_device(device_name).is_none() {
+                return Err(anyhow::anyhow!("Device not found: {}", device_name));
             }
         }
-        
-        Err(anyhow::anyhow!("Device not found: {}", device_name))
+        route.volume = route.volume.clamp(0.0, 1.0);
+        if route.device.is_some() {
+            self.clear_missing_device(&route.name);
+        }
+
+        let mut routes = self.routes.lock().unwrap();
+        if let Some(device_name) = &route.device {
+            check_device_free(&routes, &route.name, device_name)?;
+        }
+        match routes.iter_mut().find(|r| r.name == route.name) {
+            Some(existing) => *existing = route,
+            None => routes.push(route),
+        }
+        Ok(())
+    }
+
+    pub fn remove_route(&self, name: &str) -> Result<()> {
+        if name == VIRTUAL_ROUTE || name == OUTPUT_ROUTE {
+            return Err(anyhow::anyhow!("The {} route cannot be removed", name));
+        }
+        let mut routes = self.routes.lock().unwrap();
+        let count = routes.len();
+        routes.retain(|r| r.name != name);
+        if routes.len() == count {
+            return Err(anyhow::anyhow!("Route not found: {}", name));
+        }
+        Ok(())
+    }
+
+    fn update_route<F: FnOnce(&mut OutputRoute)>(&self, name: &str, update: F) -> Result<()> {
+        let mut routes = self.routes.lock().unwrap();
+        let route = routes.iter_mut().find(|r| r.name == name).context("Route not found")?;
+        update(route);
+        Ok(())
+    }
+
+    pub fn set_route_device(&self, name: &str, device_name: &str) -> Result<()> {
+        if self.find_output_device(device_name).is_none() {
+            return Err(anyhow::anyhow!("Device not found: {}", device_name));
+        }
+        {
+            let mut routes = self.routes.lock().unwrap();
+            check_device_free(&routes, name, device_name)?;
+            let route = routes.iter_mut().find(|r| r.name == name).context("Route not found")?;
+            route.device = Some(device_name.to_string());
+        }
+        self.clear_missing_device(name);
+        info!("Set {} route device to: {}", name, device_name);
+        Ok(())
+    }
+
+    pub fn set_route_volume(&self, name: &str, volume: f32) -> Result<()> {
+        self.update_route(name, |route| route.volume = volume.clamp(0.0, 1.0))
+    }
+
+    pub fn set_route_muted(&self, name: &str, muted: bool) -> Result<()> {
+        self.update_route(name, |route| route.muted = muted)
+    }
+
+    pub fn set_route_channel_mode(&self, name: &str, mode: ChannelMode) -> Result<()> {
+        self.update_route(name, |route| route.channel_mode = mode)
+    }
+
+    fn route_device(&self, name: &str) -> Option<Device> {
+        let device_name = self.get_route(name)?.device?;
+        self.find_output_device(&device_name)
+    }
+
+    fn route_volume(&self, name: &str) -> f32 {
+        self.get_route(name).map(|route| route.volume).unwrap_or(1.0)
+    }
+
+    // the virtual cable gets its own limiter, every other route shares the output one
+    pub fn route_limiter(&self, name: &str) -> Arc<LimiterSettings> {
+        if name == VIRTUAL_ROUTE {
+            self.virtual_limiter()
+        } else {
+            self.output_limiter()
+        }
+    }
+
+    pub fn set_virtual_device(&self, device_name: &str) -> Result<()> {
+        self.set_route_device(VIRTUAL_ROUTE, device_name)
     }
 
     fn set_device_from_iterator<I>(&self, device_ref: &Arc<Mutex<Option<Device>>>, devices: I, device_name: &str, device_type: &str) -> Result<()>
@@ -115,13 +295,14 @@ impl AudioManager {
     }
 
     pub fn set_output_device(&self, device_name: &str) -> Result<()> {
-        let devices = self.host.output_devices()?;
-        self.set_device_from_iterator(&self.output_device, devices, device_name, "output")
+        self.set_route_device(OUTPUT_ROUTE, device_name)
     }
 
     pub fn set_input_device(&self, device_name: &str) -> Result<()> {
         let devices = self.host.input_devices()?;
-        self.set_device_from_iterator(&self.input_device, devices, device_name, "input")
+        self.set_device_from_iterator(&self.input_device, devices, device_name, "input")?;
+        self.clear_missing_device(INPUT_BUS);
+        Ok(())
     }
 
     fn get_volume(&self, volume_ref: &Arc<Mutex<f32>>) -> f32 {
@@ -135,19 +316,19 @@ impl AudioManager {
     }
 
     pub fn get_virtual_volume(&self) -> f32 {
-        self.get_volume(&self.virtual_volume)
+        self.route_volume(VIRTUAL_ROUTE)
     }
 
     pub fn set_virtual_volume(&self, volume: f32) -> Result<()> {
-        self.set_volume(&self.virtual_volume, volume)
+        self.set_route_volume(VIRTUAL_ROUTE, volume)
     }
 
     pub fn get_output_volume(&self) -> f32 {
-        self.get_volume(&self.output_volume)
+        self.route_volume(OUTPUT_ROUTE)
     }
 
     pub fn set_output_volume(&self, volume: f32) -> Result<()> {
-        self.set_volume(&self.output_volume, volume)
+        self.set_route_volume(OUTPUT_ROUTE, volume)
     }
 
     pub fn get_input_volume(&self) -> f32 {
@@ -158,22 +339,98 @@ impl AudioManager {
         self.set_volume(&self.input_volume, volume)
     }
 
+    pub fn virtual_limiter(&self) -> Arc<LimiterSettings> {
+        self.virtual_limiter.clone()
+    }
+
+    pub fn output_limiter(&self) -> Arc<LimiterSettings> {
+        self.output_limiter.clone()
+    }
+
+    pub fn mic_processing(&self) -> Arc<MicProcessingSettings> {
+        self.mic_processing.clone()
+    }
+
+    pub fn mic_state(&self) -> MicState {
+        *self.mic_state.lock().unwrap()
+    }
+
+    // applies a hotkey's change to the mic, the passthrough follows on its next frame
+    pub fn update_mic_state(&self, update: impl FnOnce(&mut MicState)) -> MicState {
+        let mut state = self.mic_state.lock().unwrap();
+        let before = *state;
+        update(&mut state);
+        state.open = state.is_open();
+        self.mic_open.store(state.open, Ordering::SeqCst);
+        if *state != before {
+            info!("Mic {}", if state.open { "open" } else { "closed" });
+            emit_event("mic-state-changed", *state);
+        }
+        *state
+    }
+
+    // shared with the engine, which decides when to duck, and the mic voice, which follows it
+    pub fn ducking(&self) -> Arc<DuckingControl> {
+        self.ducking.clone()
+    }
+
     fn get_device(&self, device_ref: &Arc<Mutex<Option<Device>>>) -> Option<Device> {
         device_ref.lock().unwrap().clone()
     }
 
     pub fn get_virtual_device(&self) -> Option<cpal::Device> {
-        self.get_device(&self.virtual_device)
+        self.route_device(VIRTUAL_ROUTE)
     }
 
     pub fn get_output_device(&self) -> Option<cpal::Device> {
-        self.get_device(&self.output_device)
+        self.route_device(OUTPUT_ROUTE)
     }
 
     pub fn get_input_device(&self) -> Option<cpal::Device> {
         self.get_device(&self.input_device)
     }
 
+    pub fn record_missing_device(&self, missing: MissingDevice) {
+        let mut missing_devices = self.missing_devices.lock().unwrap();
+        missing_devices.retain(|m| m.bus != missing.bus);
+        missing_devices.push(missing);
+    }
+
+    pub fn missing_devices(&self) -> Vec<MissingDevice> {
+        self.missing_devices.lock().unwrap().clone()
+    }
+
+    // moves buses back onto their saved devices once those show up again, returns the buses that moved
+    pub fn rebind_missing_devices(&self, outputs: &[String], inputs: &[String]) -> Vec<String> {
+        let mut rebound = Vec::new();
+        for missing in self.missing_devices() {
+            let result = if missing.bus == INPUT_BUS {
+                if !inputs.contains(&missing.device) {
+                    continue;
+                }
+                self.set_input_device(&missing.device)
+            } else {
+                if !outputs.contains(&missing.device) {
+                    continue;
+                }
+                self.set_route_device(&missing.bus, &missing.device)
+            };
+            match result {
+                Ok(()) => {
+                    info!("Re-bound {} to {}", missing.bus, missing.device);
+                    rebound.push(missing.bus);
+                }
+                Err(e) => warn!("Could not re-bind {} to {}: {}", missing.bus, missing.device, e),
+            }
+        }
+        rebound
+    }
+
+    // picking a device for the bus resolves whatever was missing on it
+    fn clear_missing_device(&self, bus: &str) {
+        self.missing_devices.lock().unwrap().retain(|m| m.bus != bus);
+    }
+
     pub fn set_playback_position(&self, sound_id: &str, position: f32) {
         self.playback_positions.lock().unwrap().insert(sound_id.to_string(), position);
         self.playback_start_times.lock().unwrap().insert(sound_id.to_string(), std::time::Instant::now());
@@ -184,16 +441,19 @@ impl AudioManager {
         self.playback_start_times.lock().unwrap().remove(sound_id);
     }
 
+    // captures the input device and hands it to the engine, which mixes it into the virtual device's stream
     pub fn start_input_capture(&self) -> Result<()> {
         let _ = self.stop_input_capture();
 
         let input_name = self.get_input_device().and_then(|d| d.name().ok()).context("No input device set")?;
-        let output_name = self.get_virtual_device().and_then(|d| d.name().ok()).context("No virtual output device set")?;
+        self.get_route(VIRTUAL_ROUTE).and_then(|route| route.device).context("No virtual output device set")?;
 
         let stop_flag = Arc::new(AtomicBool::new(false));
         let stop_flag_clone = stop_flag.clone();
         let input_volume_ref = self.input_volume.clone();
-        let virtual_volume_ref = self.virtual_volume.clone();
+        let processing = self.mic_processing.clone();
+        let ducking = self.ducking.clone();
+        let open = self.mic_open.clone();
 
         let handle = std::thread::spawn(move || {
             let host = cpal::default_host();
@@ -204,28 +464,11 @@ impl AudioManager {
                     return;
                 }
             };
-            let output_device = match host.output_devices().ok().and_then(|mut it| it.find(|d| d.name().ok().as_deref() == Some(&output_name))) {
-                Some(d) => d,
-                None => {
-                    tracing::error!("Output device not found at runtime: {}", output_name);
-                    return;
-                }
-            };
 
             let input_config = match input_device.default_input_config() { Ok(c) => c, Err(e) => { tracing::error!("default_input_config failed: {}", e); return; } };
             let input_sample_rate = input_config.sample_rate().0;
             let input_channels = input_config.channels() as usize;
 
-            let output_config = match output_device.supported_output_configs() {
-                Ok(ranges) => {
-                    let mut chosen: Option<SupportedStreamConfigRange> = None;
-                    for r in ranges { if r.min_sample_rate().0 <= input_sample_rate && r.max_sample_rate().0 >= input_sample_rate { chosen = Some(r); break; } }
-                    chosen.map(|r| r.with_sample_rate(cpal::SampleRate(input_sample_rate))).unwrap_or_else(|| output_device.default_output_config().expect("default_output_config"))
-                }
-                Err(_) => match output_device.default_output_config() { Ok(c) => c, Err(e) => { tracing::error!("default_output_config failed: {}", e); return; } },
-            };
-            let output_channels = output_config.channels() as usize;
-
             let buffer: Arc<Mutex<VecDeque<f32>>> = Arc::new(Mutex::new(VecDeque::with_capacity((input_sample_rate as usize) * input_channels * 2)));
 
             let buffer_in = buffer.clone();
@@ -234,42 +477,34 @@ impl AudioManager {
                 let error_fn = move |err| { tracing::error!("Input stream error: {:?}", err); };
                 
                 match input_config.sample_format() {
-                    SampleFormat::F32 => input_device.build_input_stream(&cfg, create_input_stream_callback(buffer_in, |x: f32| x), error_fn, None),
-                    SampleFormat::I16 => input_device.build_input_stream(&cfg, create_input_stream_callback(buffer_in, |x: i16| x as f32 / i16::MAX as f32), error_fn, None),
-                    SampleFormat::U16 => input_device.build_input_stream(&cfg, create_input_stream_callback(buffer_in, |x: u16| (x as f32 - 32768.0) / 32768.0), error_fn, None),
-                    SampleFormat::I8 => input_device.build_input_stream(&cfg, create_input_stream_callback(buffer_in, |x: i8| x as f32 / i8::MAX as f32), error_fn, None),
-                    SampleFormat::U8 => input_device.build_input_stream(&cfg, create_input_stream_callback(buffer_in, |x: u8| (x as f32 - 128.0) / 128.0), error_fn, None),
-                    SampleFormat::I32 => input_device.build_input_stream(&cfg, create_input_stream_callback(buffer_in, |x: i32| x as f32 / i32::MAX as f32), error_fn, None),
-                    SampleFormat::U32 => input_device.build_input_stream(&cfg, create_input_stream_callback(buffer_in, |x: u32| (x as f32 - 2147483648.0) / 2147483648.0), error_fn, None),
-                    SampleFormat::F64 => input_device.build_input_stream(&cfg, create_input_stream_callback(buffer_in, |x: f64| x as f32), error_fn, None),
+                    SampleFormat::F32 => input_device.build_input_stream(&cfg, create_input_stream_callback(buffer_in, input_channels, |x: f32| x), error_fn, None),
+                    SampleFormat::I16 => input_device.build_input_stream(&cfg, create_input_stream_callback(buffer_in, input_channels, |x: i16| x as f32 / i16::MAX as f32), error_fn, None),
+                    SampleFormat::U16 => input_device.build_input_stream(&cfg, create_input_stream_callback(buffer_in, input_channels, |x: u16| (x as f32 - 32768.0) / 32768.0), error_fn, None),
+                    SampleFormat::I8 => input_device.build_input_stream(&cfg, create_input_stream_callback(buffer_in, input_channels, |x: i8| x as f32 / i8::MAX as f32), error_fn, None),
+                    SampleFormat::U8 => input_device.build_input_stream(&cfg, create_input_stream_callback(buffer_in, input_channels, |x: u8| (x as f32 - 128.0) / 128.0), error_fn, None),
+                    SampleFormat::I32 => input_device.build_input_stream(&cfg, create_input_stream_callback(buffer_in, input_channels, |x: i32| x as f32 / i32::MAX as f32), error_fn, None),
+                    SampleFormat::U32 => input_device.build_input_stream(&cfg, create_input_stream_callback(buffer_in, input_channels, |x: u32| (x as f32 - 2147483648.0) / 2147483648.0), error_fn, None),
+                    SampleFormat::F64 => input_device.build_input_stream(&cfg, create_input_stream_callback(buffer_in, input_channels, |x: f64| x as f32), error_fn, None),
                     _ => { tracing::error!("Unsupported input sample format"); return; }
                 }
             };
 
-            let buffer_out = buffer.clone();
-            let output_stream = {
-                    let cfg: StreamConfig = output_config.clone().into();
-                let error_fn = move |err| { tracing::error!("Output stream error: {:?}", err); };
-                
-                match output_config.sample_format() {
-                    SampleFormat::F32 => output_device.build_output_stream(&cfg, create_output_stream_callback(buffer_out, input_volume_ref, virtual_volume_ref, input_channels, output_channels, |x: f32| x), error_fn, None),
-                    SampleFormat::I8 => output_device.build_output_stream(&cfg, create_output_stream_callback(buffer_out, input_volume_ref, virtual_volume_ref, input_channels, output_channels, i8::from_sample), error_fn, None),
-                    SampleFormat::U8 => output_device.build_output_stream(&cfg, create_output_stream_callback(buffer_out, input_volume_ref, virtual_volume_ref, input_channels, output_channels, u8::from_sample), error_fn, None),
-                    SampleFormat::I16 => output_device.build_output_stream(&cfg, create_output_stream_callback(buffer_out, input_volume_ref, virtual_volume_ref, input_channels, output_channels, i16::from_sample), error_fn, None),
-                    SampleFormat::U16 => output_device.build_output_stream(&cfg, create_output_stream_callback(buffer_out, input_volume_ref, virtual_volume_ref, input_channels, output_channels, u16::from_sample), error_fn, None),
-                    SampleFormat::I32 => output_device.build_output_stream(&cfg, create_output_stream_callback(buffer_out, input_volume_ref, virtual_volume_ref, input_channels, output_channels, i32::from_sample), error_fn, None),
-                    SampleFormat::U32 => output_device.build_output_stream(&cfg, create_output_stream_callback(buffer_out, input_volume_ref, virtual_volume_ref, input_channels, output_channels, u32::from_sample), error_fn, None),
-                    SampleFormat::F64 => output_device.build_output_stream(&cfg, create_output_stream_callback(buffer_out, input_volume_ref, virtual_volume_ref, input_channels, output_channels, f64::from_sample), error_fn, None),
-                    _ => { tracing::error!("Unsupported output sample format"); return; }
-                }
-            };
-
             let input_stream = match input_stream { Ok(s) => s, Err(e) => { tracing::error!("Failed to build input stream: {}", e); return; } };
-            let output_stream = match output_stream { Ok(s) => s, Err(e) => { tracing::error!("Failed to build output stream: {}", e); return; } };
             if let Err(e) = input_stream.play() { tracing::error!("Failed to start input stream: {}", e); return; }
-            if let Err(e) = output_stream.play() { tracing::error!("Failed to start output stream: {}", e); return; }
 
-            info!("Started input capture: {} ch @ {} Hz -> {} ch", input_channels, input_sample_rate, output_channels);
+            get_audio_engine().send_command(AudioCommand::AttachInput {
+                input: MicInput {
+                    buffer,
+                    channels: input_channels as u16,
+                    sample_rate: input_sample_rate,
+                    volume: input_volume_ref,
+                    processing,
+                    ducking,
+                    open,
+                },
+            });
+
+            info!("Started input capture: {} ch @ {} Hz", input_channels, input_sample_rate);
             while !stop_flag_clone.load(Ordering::SeqCst) { std::thread::sleep(std::time::Duration::from_millis(50)); }
             info!("Input capture thread exiting");
         });
@@ -278,11 +513,16 @@ impl AudioManager {
         Ok(())
     }
 
+    pub fn is_input_capturing(&self) -> bool {
+        self.input_capture.lock().unwrap().is_some()
+    }
+
     pub fn stop_input_capture(&self) -> Result<()> {
         let mut guard = self.input_capture.lock().unwrap();
         if let Some(control) = guard.as_mut() {
             control.stop_flag.store(true, Ordering::SeqCst);
             if let Some(handle) = control.join_handle.take() { let _ = handle.join(); }
+            get_audio_engine().send_command(AudioCommand::DetachInput);
         }
         *guard = None;
         info!("Stopped input capture");
@@ -303,73 +543,24 @@ struct InputCaptureControl {
 
 fn create_input_stream_callback<T>(
     buffer: Arc<Mutex<VecDeque<f32>>>,
+    channels: usize,
     converter: fn(T) -> f32,
 ) -> impl Fn(&[T], &cpal::InputCallbackInfo) + Send + 'static
 where
     T: Sample + Send + 'static,
 {
+    let limit = buffer.lock().unwrap().capacity();
     move |data: &[T], _| {
         let mut buf = buffer.lock().unwrap();
-        if buf.len() > buf.capacity().saturating_sub(data.len()) {
-            let drain = data.len().min(1024);
-            buf.drain(..drain);
+        // the mic source keeps the fill level near its target, so this only trips when nothing is
+        // pulling at all. oldest whole frames go first so channels stay interleaved
+        let overflow = (buf.len() + data.len()).saturating_sub(limit);
+        if overflow > 0 {
+            let excess = (overflow.div_ceil(channels.max(1)) * channels.max(1)).min(buf.len());
+            buf.drain(..excess);
         }
         for &sample in data {
             buf.push_back(converter(sample));
         }
     }
 }
-
-fn create_output_stream_callback<T>(
-    buffer: Arc<Mutex<VecDeque<f32>>>,
-    input_volume_ref: Arc<Mutex<f32>>,
-    virtual_volume_ref: Arc<Mutex<f32>>,
-    input_channels: usize,
-    output_channels: usize,
-    converter: fn(f32) -> T,
-) -> impl Fn(&mut [T], &cpal::OutputCallbackInfo) + Send + 'static
-where
-    T: Sample + Send + 'static,
-{
-    move |data: &mut [T], _| {
-        let input_vol = *input_volume_ref.lock().unwrap();
-        let virt_vol = *virtual_volume_ref.lock().unwrap();
-        let vol = (input_vol * virt_vol).clamp(0.0, 1.0);
-        
-        if std::mem::size_of::<T>() == std::mem::size_of::<f32>() {
-            let f32_data = unsafe { 
-                std::slice::from_raw_parts_mut(data.as_mut_ptr() as *mut f32, data.len())
-            };
-            let mut buf = buffer.lock().unwrap();
-            fill_output_from_buffer(f32_data, &mut buf, input_channels, output_channels, vol);
-        } else {
-            let mut fbuf = vec![0.0f32; data.len()];
-            {
-                let mut buf = buffer.lock().unwrap();
-                fill_output_from_buffer(&mut fbuf[..], &mut buf, input_channels, output_channels, vol);
-            }
-            for (d, s) in data.iter_mut().zip(fbuf.iter()) {
-                *d = converter(*s);
-            }
-        }
-    }
-}
-
-fn fill_output_from_buffer(data: &mut [f32], buffer: &mut VecDeque<f32>, in_ch: usize, out_ch: usize, vol: f32) {
-    let frames = data.len() / out_ch;
-    for frame_idx in 0..frames {
-        // Gather one input frame
-        let mut inputs: Vec<f32> = vec![0.0; in_ch];
-        for c in 0..in_ch {
-            if let Some(s) = buffer.pop_front() { inputs[c] = s; } else { inputs[c] = 0.0; }
-        }
-        // Map to output channels
-        for c in 0..out_ch {
-            let sample = if in_ch == 0 { 0.0 } else if in_ch == out_ch { inputs[c] } else if in_ch == 1 { inputs[0] } else if out_ch == 1 { inputs.iter().sum::<f32>() / in_ch as f32 } else {
-                // use first two channels or average
-                if c < in_ch { inputs[c] } else { inputs.iter().take(2).sum::<f32>() / 2.0f32 }
-            };
-            data[frame_idx * out_ch + c] = (sample * vol).clamp(-1.0, 1.0);
-        }
-    }
-}
\ No newline at end of file
@@ -0,0 +1,431 @@
+use cpal::traits::DeviceTrait;
+use rodio::dynamic_mixer::{self, DynamicMixer, DynamicMixerController};
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::{Arc, atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering}},
+    time::Duration,
+};
+use tracing::{error, info};
+use crate::audio::{Limiter, LimiterSettings, SeekableSource};
+
+// how a route lays out the first two channels, for apps that take the virtual mic as mono
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelMode {
+    #[default]
+    Stereo,
+    // left and right averaged, so hard-panned content isn't lost
+    Mono,
+    Swap,
+}
+
+impl ChannelMode {
+    fn as_u8(self) -> u8 {
+        match self {
+            ChannelMode::Stereo => 0,
+            ChannelMode::Mono => 1,
+            ChannelMode::Swap => 2,
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => ChannelMode::Mono,
+            2 => ChannelMode::Swap,
+            _ => ChannelMode::Stereo,
+        }
+    }
+}
+
+// spreads one input frame over the output channels. equal counts copy straight across, mono is
+// duplicated, a mono output gets the average, otherwise the first channels are kept and the rest
+// get the average of the first two
+pub(crate) fn map_channels(inputs: &[f32], outputs: &mut [f32]) {
+    let in_ch = inputs.len();
+    let out_ch = outputs.len();
+    for (c, output) in outputs.iter_mut().enumerate() {
+        *output = if in_ch == 0 {
+            0.0
+        } else if in_ch == out_ch {
+            inputs[c]
+        } else if in_ch == 1 {
+            inputs[0]
+        } else if out_ch == 1 {
+            inputs.iter().sum::<f32>() / in_ch as f32
+        } else if c < in_ch {
+            inputs[c]
+        } else {
+            inputs.iter().take(2).sum::<f32>() / 2.0
+        };
+    }
+}
+
+// shared between the engine thread (writes) and the device callback (reads)
+pub struct VoiceControl {
+    volume: AtomicU32,
+    // -1.0 is hard left, 1.0 hard right
+    pan: AtomicU32,
+    channel_mode: AtomicU8,
+    paused: AtomicBool,
+    seek_pending: AtomicBool,
+    seek_target: AtomicU32,
+    stopped: AtomicBool,
+    stop_fade_ms: AtomicU32,
+    finished: AtomicBool,
+}
+
+impl VoiceControl {
+    pub fn new(volume: f32) -> Self {
+        Self {
+            volume: AtomicU32::new(volume.to_bits()),
+            pan: AtomicU32::new(0f32.to_bits()),
+            channel_mode: AtomicU8::new(ChannelMode::Stereo.as_u8()),
+            paused: AtomicBool::new(false),
+            seek_pending: AtomicBool::new(false),
+            seek_target: AtomicU32::new(0),
+            stopped: AtomicBool::new(false),
+            stop_fade_ms: AtomicU32::new(0),
+            finished: AtomicBool::new(false),
+        }
+    }
+
+    pub fn volume(&self) -> f32 {
+        f32::from_bits(self.volume.load(Ordering::Relaxed))
+    }
+
+    pub fn set_volume(&self, volume: f32) {
+        self.volume.store(volume.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn pan(&self) -> f32 {
+        f32::from_bits(self.pan.load(Ordering::Relaxed))
+    }
+
+    pub fn set_pan(&self, pan: f32) {
+        self.pan.store(pan.clamp(-1.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn channel_mode(&self) -> ChannelMode {
+        ChannelMode::from_u8(self.channel_mode.load(Ordering::Relaxed))
+    }
+
+    pub fn set_channel_mode(&self, mode: ChannelMode) {
+        self.channel_mode.store(mode.as_u8(), Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::SeqCst);
+    }
+
+    // picked up by the voice on its next frame, on the device thread. a streamed source needs its
+    // SeekSlot prepared first, the voice only swaps in what's been opened there
+    pub fn seek(&self, position: f32) {
+        self.seek_target.store(position.to_bits(), Ordering::SeqCst);
+        self.seek_pending.store(true, Ordering::SeqCst);
+    }
+
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+
+    // ramps the voice down before it ends, a paused voice just stops
+    pub fn stop_with_fade(&self, fade_ms: u32) {
+        if fade_ms == 0 || self.paused.load(Ordering::SeqCst) {
+            self.stop();
+        } else {
+            self.stop_fade_ms.store(fade_ms, Ordering::SeqCst);
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::SeqCst)
+    }
+
+    fn mark_finished(&self) {
+        self.finished.store(true, Ordering::SeqCst);
+    }
+}
+
+pub struct Voice<S> {
+    inner: S,
+    control: Arc<VoiceControl>,
+    frame_pos: usize,
+    silent_frame: bool,
+    // (total, left) frames of a fade-out started by stop_with_fade
+    stop_fade: Option<(u32, u32)>,
+}
+
+impl<S> Voice<S> {
+    pub fn new(inner: S, control: Arc<VoiceControl>) -> Self {
+        Self { inner, control, frame_pos: 0, silent_frame: false, stop_fade: None }
+    }
+}
+
+impl<S> Iterator for Voice<S>
+where
+    S: SeekableSource,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.control.stopped.load(Ordering::Relaxed) {
+            self.control.mark_finished();
+            return None;
+        }
+        // paused voices hold their place in the mixer and output silence without pulling from the decoder,
+        // only switching on frame boundaries so channels stay interleaved correctly
+        if self.frame_pos == 0 {
+            if self.control.seek_pending.swap(false, Ordering::SeqCst) {
+                let position = f32::from_bits(self.control.seek_target.load(Ordering::SeqCst));
+                if let Err(e) = self.inner.seek(position) {
+                    error!("Failed to seek voice to {:.3}s: {}", position, e);
+                }
+            }
+            self.silent_frame = self.control.paused.load(Ordering::Relaxed);
+            match self.stop_fade.as_mut() {
+                Some((_, 0)) => {
+                    self.control.mark_finished();
+                    return None;
+                }
+                Some((_, left)) => *left -= 1,
+                None => {
+                    let fade_ms = self.control.stop_fade_ms.load(Ordering::Relaxed);
+                    if fade_ms > 0 {
+                        let frames = (fade_ms as u64 * self.inner.sample_rate() as u64 / 1000).max(1) as u32;
+                        self.stop_fade = Some((frames, frames));
+                    }
+                }
+            }
+        }
+        self.frame_pos = (self.frame_pos + 1) % self.inner.channels().max(1) as usize;
+        if self.silent_frame {
+            return Some(0.0);
+        }
+        let fade = match self.stop_fade {
+            Some((total, left)) => left as f32 / total as f32,
+            None => 1.0,
+        };
+        match self.inner.next() {
+            Some(sample) => Some(sample * self.control.volume() * fade),
+            None => {
+                self.control.mark_finished();
+                None
+            }
+        }
+    }
+}
+
+impl<S> Source for Voice<S>
+where
+    S: SeekableSource,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+// converts a voice to the device's channel count itself instead of leaving it to the mixer, so the
+// sound's pan and the route's channel mode can be applied on the device layout
+pub struct ChannelMap<S> {
+    inner: S,
+    control: Arc<VoiceControl>,
+    input: Vec<f32>,
+    frame: Vec<f32>,
+    frame_pos: usize,
+}
+
+impl<S> ChannelMap<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(inner: S, control: Arc<VoiceControl>, channels: u16) -> Self {
+        let channels = channels.max(1) as usize;
+        Self {
+            input: vec![0.0; inner.channels().max(1) as usize],
+            inner,
+            control,
+            frame: vec![0.0; channels],
+            frame_pos: channels,
+        }
+    }
+}
+
+impl<S> Iterator for ChannelMap<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.frame_pos >= self.frame.len() {
+            for sample in self.input.iter_mut() {
+                *sample = self.inner.next()?;
+            }
+            map_channels(&self.input, &mut self.frame);
+            if self.frame.len() >= 2 {
+                // balance rather than constant power, a centred sound stays at full level
+                let pan = self.control.pan();
+                self.frame[0] *= (1.0 - pan).min(1.0);
+                self.frame[1] *= (1.0 + pan).min(1.0);
+                match self.control.channel_mode() {
+                    ChannelMode::Stereo => {}
+                    ChannelMode::Mono => {
+                        let mid = (self.frame[0] + self.frame[1]) / 2.0;
+                        self.frame[0] = mid;
+                        self.frame[1] = mid;
+                    }
+                    ChannelMode::Swap => self.frame.swap(0, 1),
+                }
+            }
+            self.frame_pos = 0;
+        }
+        let sample = self.frame[self.frame_pos];
+        self.frame_pos += 1;
+        Some(sample)
+    }
+}
+
+impl<S> Source for ChannelMap<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.frame.len() as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+// rodio's DynamicMixer ends as soon as it runs out of sources, the bus outputs silence instead
+// so it stays attached to the device stream between sounds. the summed mix goes through the
+// device's limiter a frame at a time
+pub struct MixerBus {
+    mixer: DynamicMixer<f32>,
+    limiter: Limiter,
+    limiter_settings: Arc<LimiterSettings>,
+    frame: Vec<f32>,
+    frame_pos: usize,
+}
+
+impl MixerBus {
+    fn new(mixer: DynamicMixer<f32>, limiter_settings: Arc<LimiterSettings>) -> Self {
+        let channels = mixer.channels().max(1) as usize;
+        let limiter = Limiter::new(mixer.channels(), mixer.sample_rate());
+        Self {
+            mixer,
+            limiter,
+            limiter_settings,
+            frame: vec![0.0; channels],
+            frame_pos: channels,
+        }
+    }
+}
+
+impl Iterator for MixerBus {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.frame_pos >= self.frame.len() {
+            for sample in self.frame.iter_mut() {
+                *sample = self.mixer.next().unwrap_or(0.0);
+            }
+            self.limiter.process(&mut self.frame, &self.limiter_settings);
+            self.frame_pos = 0;
+        }
+        let sample = self.frame[self.frame_pos];
+        self.frame_pos += 1;
+        Some(sample)
+    }
+}
+
+impl Source for MixerBus {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.mixer.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.mixer.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+// one long-lived output stream per device, voices are added to and removed from its mixer
+pub struct DeviceMixer {
+    controller: Arc<DynamicMixerController<f32>>,
+    channels: u16,
+    sample_rate: u32,
+    // None when something other than a sound card pulls the bus
+    _stream: Option<rodio::OutputStream>,
+}
+
+impl DeviceMixer {
+    // a mixer whose bus is handed back to the caller instead of being attached to a device
+    pub fn detached(channels: u16, sample_rate: u32, limiter_settings: Arc<LimiterSettings>) -> (Self, MixerBus) {
+        let (controller, mixer) = dynamic_mixer::mixer::<f32>(channels, sample_rate);
+        let mixer_handle = Self { controller, channels, sample_rate, _stream: None };
+        (mixer_handle, MixerBus::new(mixer, limiter_settings))
+    }
+
+    pub fn open(device: &cpal::Device, limiter_settings: Arc<LimiterSettings>) -> Result<Self, String> {
+        let name = device.name().unwrap_or_default();
+        let config = device
+            .default_output_config()
+            .map_err(|e| format!("Failed to get output config for {}: {}", name, e))?;
+        let (stream, handle) = rodio::OutputStream::try_from_device_config(device, config.clone())
+            .map_err(|e| format!("Failed to create output stream for {}: {}", name, e))?;
+
+        let (mut mixer, bus) = Self::detached(config.channels(), config.sample_rate().0, limiter_settings);
+        handle
+            .play_raw(bus)
+            .map_err(|e| format!("Failed to attach mixer to {}: {}", name, e))?;
+        mixer._stream = Some(stream);
+
+        info!("Opened mixer stream for {} ({} ch @ {} Hz)", name, config.channels(), config.sample_rate().0);
+        Ok(mixer)
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn add_voice<S>(&self, source: S, control: Arc<VoiceControl>)
+    where
+        S: SeekableSource + Send + 'static,
+    {
+        self.controller.add(ChannelMap::new(Voice::new(source, control.clone()), control, self.channels));
+    }
+}
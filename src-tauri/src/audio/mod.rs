@@ -1,9 +1,31 @@
+pub mod backend;
+pub mod cache;
+pub mod ducking;
+pub mod dynamics;
+pub mod effects;
 pub mod engine;
+pub mod input;
+pub mod limiter;
+pub mod loudness;
+pub mod mixer;
 pub mod manager;
+pub mod render;
 pub mod source;
+pub mod watcher;
 pub mod commands;
 
+pub use backend::*;
+pub use cache::*;
+pub use ducking::*;
+pub use dynamics::*;
+pub use effects::*;
 pub use engine::*;
+pub use input::*;
+pub use limiter::*;
+pub use loudness::*;
+pub use mixer::*;
 pub use manager::*;
+pub use render::*;
 pub use source::*;
+pub use watcher::*;
 pub use commands::*; 
\ No newline at end of file
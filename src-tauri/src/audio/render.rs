@@ -0,0 +1,291 @@
+use anyhow::Result;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tracing::info;
+use crate::audio::{AudioBackend, AudioCommand, DuckingConfig, EngineCore, NullBackend, OutputRoute, VoiceLimit};
+
+// commands land on block boundaries, so this is how precise script timestamps are
+const BLOCK_MS: u32 = 10;
+// keeps rendering a little after the last voice ends so the limiter's look-ahead is flushed
+const TAIL_MS: u32 = 50;
+
+#[derive(Debug, Deserialize)]
+pub struct ScriptedCommand {
+    // seconds from the start of the render
+    pub at: f32,
+    pub command: AudioCommand,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct RenderScript {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub routes: Vec<OutputRoute>,
+    pub voice_limit: VoiceLimit,
+    pub ducking: DuckingConfig,
+    // rendering stops here even if something is still playing
+    pub max_seconds: f32,
+    pub commands: Vec<ScriptedCommand>,
+}
+
+impl Default for RenderScript {
+    fn default() -> Self {
+        Self {
+            channels: 2,
+            sample_rate: 48000,
+            routes: NullBackend::default_routes(),
+            voice_limit: VoiceLimit::default(),
+            ducking: DuckingConfig::default(),
+            max_seconds: 600.0,
+            commands: Vec::new(),
+        }
+    }
+}
+
+impl RenderScript {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+}
+
+// interleaved mix of every device the script played on, all starting at time zero
+pub struct OfflineRender {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub devices: HashMap<String, Vec<f32>>,
+}
+
+impl OfflineRender {
+    pub fn duration(&self) -> f32 {
+        let samples = self.devices.values().map(Vec::len).max().unwrap_or(0);
+        samples as f32 / self.channels.max(1) as f32 / self.sample_rate.max(1) as f32
+    }
+
+    pub fn write_wav(&self, device_name: &str, path: &Path) -> Result<()> {
+        let samples = self
+            .devices
+            .get(device_name)
+            .ok_or_else(|| anyhow::anyhow!("Nothing was rendered on {}", device_name))?;
+        let spec = hound::WavSpec {
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut writer = hound::WavWriter::create(path, spec)?;
+        for sample in samples {
+            writer.write_sample(*sample)?;
+        }
+        writer.finalize()?;
+        Ok(())
+    }
+
+    // one file per device next to base, named <base stem>-<device>.wav
+    pub fn write_all(&self, base: &Path) -> Result<Vec<PathBuf>> {
+        let stem = base.file_stem().and_then(|s| s.to_str()).unwrap_or("render");
+        let mut written = Vec::new();
+        for device_name in self.devices.keys() {
+            let safe_name: String = device_name
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+                .collect();
+            let path = base.with_file_name(format!("{}-{}.wav", stem, safe_name));
+            self.write_wav(device_name, &path)?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+}
+
+// plays the script through the engine on null devices, as fast as the files decode. each command goes
+// in at the start of the first block at or after its timestamp
+pub fn render_offline(script: RenderScript) -> OfflineRender {
+    let RenderScript { channels, sample_rate, routes, voice_limit, ducking, max_seconds, mut commands } = script;
+    commands.sort_by(|a, b| a.at.total_cmp(&b.at));
+
+    let backend = Arc::new(NullBackend::new(channels, sample_rate, routes));
+    backend.ducking().set_config(ducking);
+    let mut core = EngineCore::new(backend.clone());
+    let sample_rate = backend.sample_rate();
+    let block = (sample_rate * BLOCK_MS / 1000).max(1) as usize;
+    let max_frames = (max_seconds.max(0.0) * sample_rate as f32) as usize;
+    let mut commands = commands.into_iter().peekable();
+
+    'render: loop {
+        let now = backend.rendered_frames() as f32 / sample_rate as f32;
+        while let Some(step) = commands.next_if(|step| step.at <= now) {
+            if !core.handle_command(step.command, voice_limit) {
+                break 'render;
+            }
+        }
+        core.cleanup_finished();
+        if backend.rendered_frames() >= max_frames {
+            break;
+        }
+        if commands.peek().is_none() && core.is_idle() {
+            backend.render((sample_rate * TAIL_MS / 1000) as usize);
+            break;
+        }
+        backend.render(block);
+    }
+
+    let render = OfflineRender {
+        channels: backend.channels(),
+        sample_rate,
+        devices: backend.take_recordings(),
+    };
+    info!("Rendered {:.2}s on {} devices", render.duration(), render.devices.len());
+    render
+}
+
+// renders a JSON script and writes the mix of every device next to it
+pub fn render_script_file(path: &Path) -> Result<Vec<PathBuf>> {
+    let script = RenderScript::from_file(path)?;
+    render_offline(script).write_all(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{LoopRegion, PlayOptions, NULL_DEVICE, OUTPUT_ROUTE, VIRTUAL_ROUTE};
+
+    const RATE: u32 = 48000;
+
+    // a stereo file holding one level throughout, so the mix can be read back at any point
+    struct ConstantFile(PathBuf);
+
+    impl ConstantFile {
+        fn new(level: f32, seconds: f32) -> Self {
+            let path = std::env::temp_dir().join(format!("midah-render-{}.wav", uuid::Uuid::new_v4()));
+            let spec = hound::WavSpec {
+                channels: 2,
+                sample_rate: RATE,
+                bits_per_sample: 32,
+                sample_format: hound::SampleFormat::Float,
+            };
+            let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+            for _ in 0..(seconds * RATE as f32) as usize * 2 {
+                writer.write_sample(level).unwrap();
+            }
+            writer.finalize().unwrap();
+            Self(path)
+        }
+
+        fn play(&self, at: f32, sound_id: &str, options: PlayOptions) -> ScriptedCommand {
+            ScriptedCommand {
+                at,
+                command: AudioCommand::Play {
+                    file_path: self.0.to_string_lossy().into_owned(),
+                    sound_id: sound_id.to_string(),
+                    options,
+                },
+            }
+        }
+    }
+
+    impl Drop for ConstantFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn route(name: &str, device: &str, volume: f32, muted: bool) -> OutputRoute {
+        OutputRoute {
+            name: name.to_string(),
+            device: Some(device.to_string()),
+            volume,
+            muted,
+            channel_mode: Default::default(),
+        }
+    }
+
+    fn script(routes: Vec<OutputRoute>, commands: Vec<ScriptedCommand>) -> RenderScript {
+        RenderScript { channels: 2, sample_rate: RATE, routes, max_seconds: 5.0, commands, ..Default::default() }
+    }
+
+    // left channel of the device's mix at a time, silence if nothing ever played on it
+    fn level(render: &OfflineRender, device_name: &str, seconds: f32) -> f32 {
+        let index = (seconds * render.sample_rate as f32) as usize * render.channels as usize;
+        render.devices.get(device_name).and_then(|samples| samples.get(index)).copied().unwrap_or(0.0)
+    }
+
+    fn peak(render: &OfflineRender, device_name: &str) -> f32 {
+        render
+            .devices
+            .get(device_name)
+            .map_or(0.0, |samples| samples.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs())))
+    }
+
+    fn assert_level(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-3, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn overlapping_sounds_sum() {
+        let quiet = ConstantFile::new(0.2, 1.0);
+        let loud = ConstantFile::new(0.3, 1.0);
+        let render = render_offline(script(
+            NullBackend::default_routes(),
+            vec![quiet.play(0.0, "quiet", PlayOptions::default()), loud.play(0.5, "loud", PlayOptions::default())],
+        ));
+
+        assert_level(level(&render, NULL_DEVICE, 0.25), 0.2);
+        assert_level(level(&render, NULL_DEVICE, 0.75), 0.5);
+        assert_level(level(&render, NULL_DEVICE, 1.25), 0.3);
+        assert_level(level(&render, NULL_DEVICE, 1.75), 0.0);
+    }
+
+    #[test]
+    fn looping_sound_repeats_from_memory() {
+        let file = ConstantFile::new(0.3, 0.2);
+        let looped = PlayOptions { loop_region: Some(LoopRegion { start: 0.0, end: None, repeats: Some(2) }), ..Default::default() };
+        let render = render_offline(script(NullBackend::default_routes(), vec![file.play(0.0, "loop", looped)]));
+
+        assert_level(level(&render, NULL_DEVICE, 0.5), 0.3);
+        assert_level(level(&render, NULL_DEVICE, 0.7), 0.0);
+    }
+
+    #[test]
+    fn route_volume_scales_and_mute_silences() {
+        let file = ConstantFile::new(0.4, 0.5);
+        let render = render_offline(script(
+            vec![route(OUTPUT_ROUTE, "speakers", 0.5, false), route(VIRTUAL_ROUTE, "cable", 1.0, true)],
+            vec![file.play(0.0, "sound", PlayOptions::default())],
+        ));
+
+        assert_level(level(&render, "speakers", 0.25), 0.2);
+        assert_level(peak(&render, "cable"), 0.0);
+    }
+
+    #[test]
+    fn sounds_only_reach_their_routes() {
+        let virtual_only = ConstantFile::new(0.2, 0.5);
+        let output_only = ConstantFile::new(0.3, 0.5);
+        let render = render_offline(script(
+            vec![route(OUTPUT_ROUTE, "speakers", 1.0, false), route(VIRTUAL_ROUTE, "cable", 1.0, false)],
+            vec![
+                virtual_only.play(
+                    0.0,
+                    "virtual only",
+                    PlayOptions { output_routes: Some(vec![VIRTUAL_ROUTE.to_string()]), ..Default::default() },
+                ),
+                output_only.play(
+                    1.0,
+                    "output only",
+                    PlayOptions { output_routes: Some(vec![OUTPUT_ROUTE.to_string()]), ..Default::default() },
+                ),
+            ],
+        ));
+
+        assert_level(level(&render, "cable", 0.25), 0.2);
+        assert_level(level(&render, "speakers", 0.25), 0.0);
+        assert_level(level(&render, "speakers", 1.25), 0.3);
+        assert_level(level(&render, "cable", 1.25), 0.0);
+    }
+}
@@ -1,8 +1,10 @@
 use anyhow::Result;
 use rodio::Source;
+use serde::{Deserialize, Serialize};
 use std::{
     collections::VecDeque,
     fs::File,
+    sync::{Arc, Mutex, atomic::{AtomicU64, Ordering}},
 };
 use symphonia::core::{
     audio::{AudioBufferRef, Signal},
@@ -12,21 +14,140 @@ use symphonia::core::{
     io::MediaSourceStream,
     meta::MetadataOptions,
     probe::Hint,
-    units::Time,
+    units::{Time, TimeBase},
 };
 use tracing::{error, info};
 
-pub struct SymphoniaAudioSource {
+// frames handed out by a source so far, readable from other threads while it plays
+pub struct PlaybackCursor {
+    frames: AtomicU64,
+    sample_rate: u32,
+}
+
+impl PlaybackCursor {
+    fn new(frames: u64, sample_rate: u32) -> Self {
+        Self {
+            frames: AtomicU64::new(frames),
+            sample_rate,
+        }
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames.load(Ordering::Relaxed)
+    }
+
+    pub fn position(&self) -> f32 {
+        (self.frames() as f64 / self.sample_rate.max(1) as f64) as f32
+    }
+}
+
+// the part of a file a source jumps back over, no end loops at the end of the file
+// and no repeats keeps looping until the voice is stopped
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LoopRegion {
+    pub start: f32,
+    pub end: Option<f32>,
+    pub repeats: Option<u32>,
+}
+
+struct LoopState {
+    start: f32,
+    end_frame: Option<u64>,
+    repeats_left: Option<u32>,
+}
+
+// a whole clip decoded to interleaved f32, shared between the sample cache and the voices playing it
+pub struct DecodedAudio {
+    pub samples: Vec<f32>,
+    pub channels: u16,
+    pub sample_rate: u32,
+}
+
+impl DecodedAudio {
+    pub fn frames(&self) -> u64 {
+        (self.samples.len() / self.channels.max(1) as usize) as u64
+    }
+
+    pub fn size_bytes(&self) -> usize {
+        self.samples.len() * std::mem::size_of::<f32>()
+    }
+}
+
+struct StreamDecoder {
     decoder: Box<dyn symphonia::core::codecs::Decoder>,
     format: Box<dyn symphonia::core::formats::FormatReader>,
     track_id: u32,
+    time_base: Option<TimeBase>,
+}
+
+enum Backing {
+    Stream(StreamDecoder),
+    Memory(Arc<DecodedAudio>),
+}
+
+// a decoder already positioned somewhere in the file, with its first packet decoded
+struct PreparedStream {
+    backing: Backing,
+    current_ts: u64,
+    skip_frames: u64,
+    sample_buffer: VecDeque<f32>,
+}
+
+struct SeekState {
+    pending: Option<PreparedStream>,
+    // the decoder the source let go of, dropped here on the engine thread rather than in the callback
+    spent: Option<PreparedStream>,
+}
+
+// how a stream-backed source seeks without touching the file on the device thread. the engine opens
+// the file again at the target and the source swaps the new decoder in on its next frame
+pub struct SeekSlot {
+    state: Mutex<SeekState>,
+    version: AtomicU64,
+}
+
+impl SeekSlot {
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(SeekState { pending: None, spent: None }),
+            version: AtomicU64::new(0),
+        }
+    }
+
+    pub fn prepare(&self, file_path: &str, position: f32) -> Result<()> {
+        let SymphoniaAudioSource { backing, current_ts, skip_frames, sample_buffer, .. } = SymphoniaAudioSource::new(file_path, position)?;
+        let mut state = self.state.lock().unwrap();
+        let stale = (state.pending.take(), state.spent.take());
+        state.pending = Some(PreparedStream { backing, current_ts, skip_frames, sample_buffer });
+        drop(state);
+        drop(stale);
+        self.version.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+pub struct SymphoniaAudioSource {
+    backing: Backing,
     current_ts: u64,
     end_ts: Option<u64>,
     sample_rate: u32,
     channels: u16,
-    _start_position: f32,
-    _samples_skipped: usize,
+    sample_idx: usize,
+    skip_frames: u64,
+    cursor: Arc<PlaybackCursor>,
     sample_buffer: VecDeque<f32>,
+    looping: Option<LoopState>,
+    trim_end: Option<u64>,
+    fade_in_frames: u64,
+    fade_out_frames: u64,
+    frames_out: u64,
+    seek_slot: Option<Arc<SeekSlot>>,
+    seek_version: u64,
+}
+
+// sources that can jump to a new position while they are already playing
+pub trait SeekableSource: Source<Item = f32> {
+    fn seek(&mut self, position: f32) -> Result<()>;
 }
 
 impl SymphoniaAudioSource {
@@ -43,46 +164,249 @@ impl SymphoniaAudioSource {
         let fmt_opts: FormatOptions = Default::default();
         
         let probed = symphonia::default::get_probe().format(&hint, src, &fmt_opts, &meta_opts)?;
-        let mut format = probed.format;
         
-        let track = format.tracks().iter().find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        let track = probed.format.tracks().iter().find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
             .ok_or_else(|| anyhow::anyhow!("No supported audio tracks"))?;
         let track_id = track.id;
         let sample_rate = track.codec_params.sample_rate.unwrap_or(44100);
         let channels = track.codec_params.channels.map(|c| c.count()).unwrap_or(2);
         let end_ts = track.codec_params.n_frames;
+        let time_base = track.codec_params.time_base;
         
         let dec_opts: DecoderOptions = Default::default();
-        let mut decoder = symphonia::default::get_codecs().make(&track.codec_params, &dec_opts)?;
-        
-        if start_position > 0.0 {
-            format.seek(symphonia::core::formats::SeekMode::Accurate, symphonia::core::formats::SeekTo::Time {
-                time: Time { seconds: start_position as u64, frac: 0.0 },
-                track_id: Some(track_id),
-            })?;
-            
-            decoder.reset();
-        }
-        
-        let start_ts = (start_position * sample_rate as f32) as u64;
+        let decoder = symphonia::default::get_codecs().make(&track.codec_params, &dec_opts)?;
         
         let mut source = Self {
-            decoder,
-            format,
-            track_id,
-            current_ts: start_ts,
+            backing: Backing::Stream(StreamDecoder {
+                decoder,
+                format: probed.format,
+                track_id,
+                time_base,
+            }),
+            current_ts: 0,
             end_ts,
             sample_rate,
             channels: channels.try_into().unwrap_or(2),
-            _start_position: start_position,
-            _samples_skipped: 0,
+            sample_idx: 0,
+            skip_frames: 0,
+            cursor: Arc::new(PlaybackCursor::new(0, sample_rate)),
             sample_buffer: VecDeque::new(),
+            looping: None,
+            trim_end: None,
+            fade_in_frames: 0,
+            fade_out_frames: 0,
+            frames_out: 0,
+            seek_slot: None,
+            seek_version: 0,
         };
         
-        source.prime_decoder()?;
+        if start_position > 0.0 {
+            source.seek_to(start_position)?;
+        } else {
+            source.prime_decoder()?;
+        }
         
         Ok(source)
     }
+
+    // plays from PCM that's already in memory, no file access or probing
+    pub fn from_decoded(audio: Arc<DecodedAudio>, start_position: f32) -> Self {
+        let sample_rate = audio.sample_rate;
+        let mut source = Self {
+            current_ts: 0,
+            end_ts: Some(audio.frames()),
+            sample_rate,
+            channels: audio.channels,
+            backing: Backing::Memory(audio),
+            sample_idx: 0,
+            skip_frames: 0,
+            cursor: Arc::new(PlaybackCursor::new(0, sample_rate)),
+            sample_buffer: VecDeque::new(),
+            looping: None,
+            trim_end: None,
+            fade_in_frames: 0,
+            fade_out_frames: 0,
+            frames_out: 0,
+            seek_slot: None,
+            seek_version: 0,
+        };
+        if start_position > 0.0 {
+            source.seek_memory(start_position);
+        }
+        source
+    }
+
+    pub fn cursor(&self) -> Arc<PlaybackCursor> {
+        self.cursor.clone()
+    }
+
+    // only a stream-backed source has one, in memory a seek is just an index and happens in place
+    pub fn seek_slot(&mut self) -> Option<Arc<SeekSlot>> {
+        if !matches!(self.backing, Backing::Stream(_)) {
+            return None;
+        }
+        Some(self.seek_slot.get_or_insert_with(|| Arc::new(SeekSlot::new())).clone())
+    }
+
+    pub fn duration(&self) -> Option<f32> {
+        self.end_ts.map(|end_ts| end_ts as f32 / self.sample_rate as f32)
+    }
+
+    // only a source playing from memory loops, see wrap_loop
+    pub fn set_loop(&mut self, region: LoopRegion) {
+        if !matches!(self.backing, Backing::Memory(_)) {
+            return;
+        }
+        let end_frame = region
+            .end
+            .filter(|end| *end > region.start)
+            .map(|end| (end as f64 * self.sample_rate as f64).round() as u64);
+        self.looping = Some(LoopState {
+            start: region.start.max(0.0),
+            end_frame,
+            repeats_left: region.repeats,
+        });
+    }
+
+    // stops the source early and ramps it in and out, the file itself is left alone
+    pub fn set_trim(&mut self, end_position: Option<f32>, fade_in_ms: u32, fade_out_ms: u32) {
+        self.trim_end = end_position
+            .filter(|end| *end > 0.0)
+            .map(|end| (end as f64 * self.sample_rate as f64).round() as u64);
+        self.fade_in_frames = fade_in_ms as u64 * self.sample_rate as u64 / 1000;
+        self.fade_out_frames = fade_out_ms as u64 * self.sample_rate as u64 / 1000;
+    }
+
+    fn end_frame(&self) -> Option<u64> {
+        match (self.end_ts, self.trim_end) {
+            (Some(end_ts), Some(trim_end)) => Some(end_ts.min(trim_end)),
+            (end_ts, trim_end) => trim_end.or(end_ts),
+        }
+    }
+
+    fn at_trim_end(&self) -> bool {
+        self.trim_end.is_some_and(|trim_end| self.current_ts >= trim_end)
+    }
+
+    fn is_looping(&self) -> bool {
+        self.looping.as_ref().is_some_and(|state| state.repeats_left != Some(0))
+    }
+
+    fn envelope(&self) -> f32 {
+        let mut gain = 1.0;
+        if self.frames_out < self.fade_in_frames {
+            gain *= self.frames_out as f32 / self.fade_in_frames as f32;
+        }
+        if self.fade_out_frames > 0 && !self.is_looping() {
+            if let Some(end) = self.end_frame() {
+                let left = end.saturating_sub(self.current_ts);
+                if left < self.fade_out_frames {
+                    gain *= left as f32 / self.fade_out_frames as f32;
+                }
+            }
+        }
+        gain
+    }
+
+    fn at_loop_end(&self) -> bool {
+        match &self.looping {
+            Some(LoopState { end_frame: Some(end_frame), repeats_left, .. }) => {
+                *repeats_left != Some(0) && self.current_ts >= *end_frame
+            }
+            _ => false,
+        }
+    }
+
+    // jumps back to the loop start if there are repeats left, false means the source should end. this
+    // runs on the device thread at every loop boundary, so it's only ever an index reset in memory
+    fn wrap_loop(&mut self) -> bool {
+        let start = match &mut self.looping {
+            Some(state) if state.repeats_left != Some(0) => {
+                if let Some(repeats) = state.repeats_left.as_mut() {
+                    *repeats -= 1;
+                }
+                state.start
+            }
+            _ => return false,
+        };
+        self.seek_memory(start);
+        true
+    }
+
+    // accurate seek to the nearest millisecond, the decoder lands on the packet before the target
+    // and the frames in between are dropped as they are decoded. this reads the file, so it only runs
+    // where the source is opened, a playing stream seeks through its SeekSlot
+    fn seek_to(&mut self, position: f32) -> Result<()> {
+        let Backing::Stream(stream) = &mut self.backing else {
+            self.seek_memory(position);
+            return Ok(());
+        };
+        let millis = (position.max(0.0) as f64 * 1000.0).round() as u64;
+        let seeked = stream.format.seek(symphonia::core::formats::SeekMode::Accurate, symphonia::core::formats::SeekTo::Time {
+            time: Time { seconds: millis / 1000, frac: (millis % 1000) as f64 / 1000.0 },
+            track_id: Some(stream.track_id),
+        })?;
+        stream.decoder.reset();
+
+        let actual_frames = ts_to_frames(seeked.actual_ts, stream.time_base, self.sample_rate);
+        let required_frames = ts_to_frames(seeked.required_ts, stream.time_base, self.sample_rate);
+        self.skip_frames = required_frames.saturating_sub(actual_frames);
+        self.current_ts = required_frames.max(actual_frames);
+        self.cursor.frames.store(self.current_ts, Ordering::Relaxed);
+        self.sample_idx = 0;
+        self.sample_buffer.clear();
+
+        self.prime_decoder()
+    }
+
+    // in memory every frame is addressable, so a seek is exact and free
+    fn seek_memory(&mut self, position: f32) {
+        let frame = (position.max(0.0) as f64 * self.sample_rate as f64).round() as u64;
+        self.current_ts = frame.min(self.end_ts.unwrap_or(frame));
+        self.cursor.frames.store(self.current_ts, Ordering::Relaxed);
+        self.sample_idx = 0;
+        self.skip_frames = 0;
+        self.sample_buffer.clear();
+    }
+
+    // swaps in the decoder the engine opened at the seek target, a busy slot is retried next frame
+    fn take_prepared_seek(&mut self) {
+        let Some(slot) = &self.seek_slot else { return };
+        let version = slot.version.load(Ordering::SeqCst);
+        if version == self.seek_version {
+            return;
+        }
+        let Ok(mut state) = slot.state.try_lock() else { return };
+        self.seek_version = version;
+        let Some(mut prepared) = state.pending.take() else { return };
+        std::mem::swap(&mut self.backing, &mut prepared.backing);
+        std::mem::swap(&mut self.sample_buffer, &mut prepared.sample_buffer);
+        self.current_ts = prepared.current_ts;
+        self.skip_frames = prepared.skip_frames;
+        self.sample_idx = 0;
+        self.cursor.frames.store(self.current_ts, Ordering::Relaxed);
+        state.spent = Some(prepared);
+    }
+
+    fn load_buffer(&mut self, mut buffer: VecDeque<f32>) {
+        if self.skip_frames > 0 {
+            let channels = self.channels.max(1) as usize;
+            let skip = (self.skip_frames as usize).saturating_mul(channels).min(buffer.len());
+            buffer.drain(..skip);
+            self.skip_frames -= (skip / channels) as u64;
+        }
+        self.sample_buffer = buffer;
+    }
+
+    fn advance_sample(&mut self) {
+        self.sample_idx += 1;
+        if self.sample_idx >= self.channels as usize {
+            self.sample_idx = 0;
+            self.current_ts += 1;
+            self.frames_out += 1;
+            self.cursor.frames.store(self.current_ts, Ordering::Relaxed);
+        }
+    }
     
     fn decode_and_buffer(decoded: AudioBufferRef, channels: u16) -> Option<VecDeque<f32>> {
         let channels = channels as usize;
@@ -133,13 +457,16 @@ impl SymphoniaAudioSource {
     
     fn prime_decoder(&mut self) -> Result<()> {
         loop {
-            let packet = self.format.next_packet()?;
-            if packet.track_id() == self.track_id {
-                match self.decoder.decode(&packet) {
+            let Backing::Stream(stream) = &mut self.backing else { return Ok(()) };
+            let packet = stream.format.next_packet()?;
+            if packet.track_id() == stream.track_id {
+                match stream.decoder.decode(&packet) {
                     Ok(decoded) => {
                         if let Some(buffer) = Self::decode_and_buffer(decoded, self.channels) {
-                            self.sample_buffer = buffer;
-                            break;
+                            self.load_buffer(buffer);
+                            if !self.sample_buffer.is_empty() {
+                                break;
+                            }
                         }
                     }
                     Err(SymphoniaError::IoError(_)) => {
@@ -157,31 +484,51 @@ impl SymphoniaAudioSource {
 
 impl Iterator for SymphoniaAudioSource {
     type Item = f32;
-    
+
     fn next(&mut self) -> Option<Self::Item> {
-        static mut SAMPLE_IDX: usize = 0;
-        let channels = self.channels as usize;
-        
+        if self.sample_idx == 0 {
+            self.take_prepared_seek();
+        }
+        if self.sample_idx == 0 && (self.at_loop_end() || self.at_trim_end()) && !self.wrap_loop() {
+            return None;
+        }
+        let gain = self.envelope();
+        let sample = match self.next_sample() {
+            Some(sample) => sample,
+            None if self.wrap_loop() => self.next_sample()?,
+            None => return None,
+        };
+        Some(sample * gain)
+    }
+}
+
+impl SymphoniaAudioSource {
+    fn next_sample(&mut self) -> Option<f32> {
         if let Some(sample) = self.sample_buffer.pop_front() {
-            unsafe {
-                SAMPLE_IDX += 1;
-                if SAMPLE_IDX == channels {
-                    self.current_ts += 1;
-                    SAMPLE_IDX = 0;
-                }
+            self.advance_sample();
+            return Some(sample);
+        }
+
+        if let Backing::Memory(audio) = &self.backing {
+            if self.end_frame().is_some_and(|end| self.current_ts >= end) {
+                return None;
             }
+            let index = self.current_ts as usize * self.channels as usize + self.sample_idx;
+            let sample = *audio.samples.get(index)?;
+            self.advance_sample();
             return Some(sample);
         }
         
         loop {
-            if let Some(end_ts) = self.end_ts {
+            if let Some(end_ts) = self.end_frame() {
                 if self.current_ts >= end_ts {
                     info!("Audio source reached end timestamp: {} >= {}", self.current_ts, end_ts);
                     return None;
                 }
             }
+            let Backing::Stream(stream) = &mut self.backing else { return None };
             
-            let packet = match self.format.next_packet() {
+            let packet = match stream.format.next_packet() {
                 Ok(packet) => packet,
                 Err(SymphoniaError::ResetRequired) => {
                     info!("Audio source requires reset");
@@ -201,20 +548,20 @@ impl Iterator for SymphoniaAudioSource {
                 }
             };
             
-            while !self.format.metadata().is_latest() {
-                self.format.metadata().pop();
+            while !stream.format.metadata().is_latest() {
+                stream.format.metadata().pop();
             }
             
-            if packet.track_id() != self.track_id {
+            if packet.track_id() != stream.track_id {
                 continue;
             }
             
-            match self.decoder.decode(&packet) {
+            match stream.decoder.decode(&packet) {
                 Ok(decoded) => {
                     if let Some(buffer) = Self::decode_and_buffer(decoded, self.channels) {
-                        self.sample_buffer = buffer;
+                        self.load_buffer(buffer);
                         if let Some(sample) = self.sample_buffer.pop_front() {
-                            self.current_ts += 1;
+                            self.advance_sample();
                             return Some(sample);
                         } else {
                             continue;
@@ -250,8 +597,11 @@ impl Source for SymphoniaAudioSource {
     }
     
     fn total_duration(&self) -> Option<std::time::Duration> {
-        if let Some(end_ts) = self.end_ts {
-            let duration_seconds = (end_ts - self.current_ts) as f64 / self.sample_rate as f64;
+        if self.is_looping() {
+            return None;
+        }
+        if let Some(end_ts) = self.end_frame() {
+            let duration_seconds = end_ts.saturating_sub(self.current_ts) as f64 / self.sample_rate as f64;
             Some(std::time::Duration::from_secs_f64(duration_seconds))
         } else {
             None
@@ -259,6 +609,28 @@ impl Source for SymphoniaAudioSource {
     }
 }
 
+// converts a track timestamp to a frame count, most formats already count in frames
+fn ts_to_frames(ts: u64, time_base: Option<TimeBase>, sample_rate: u32) -> u64 {
+    match time_base {
+        Some(time_base) => {
+            let time = time_base.calc_time(ts);
+            ((time.seconds as f64 + time.frac) * sample_rate as f64).round() as u64
+        }
+        None => ts,
+    }
+}
+
+impl SeekableSource for SymphoniaAudioSource {
+    fn seek(&mut self, position: f32) -> Result<()> {
+        match self.backing {
+            Backing::Memory(_) => self.seek_memory(position),
+            // the engine has already opened the file at the target
+            Backing::Stream(_) => self.take_prepared_seek(),
+        }
+        Ok(())
+    }
+}
+
 pub fn get_audio_duration(file_path: &str) -> Result<f32> {
     let src = MediaSourceStream::new(Box::new(File::open(file_path)?), Default::default());
     let mut hint = Hint::new();
@@ -280,4 +652,39 @@ pub fn get_audio_duration(file_path: &str) -> Result<f32> {
         return Err(anyhow::anyhow!("Could not determine audio duration: n_frames missing"));
     };
     Ok(duration)
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a stereo file at 0.1 for its first second and 0.4 for its second
+    fn write_steps(path: &std::path::Path) {
+        let spec = hound::WavSpec { channels: 2, sample_rate: 48000, bits_per_sample: 32, sample_format: hound::SampleFormat::Float };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        for frame in 0..96000 {
+            let level = if frame < 48000 { 0.1 } else { 0.4 };
+            writer.write_sample(level).unwrap();
+            writer.write_sample(level).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    #[test]
+    fn streamed_seek_swaps_in_the_prepared_decoder() {
+        let path = std::env::temp_dir().join(format!("midah-seek-{}.wav", uuid::Uuid::new_v4()));
+        write_steps(&path);
+        let file_path = path.to_string_lossy().into_owned();
+
+        let mut source = SymphoniaAudioSource::new(&file_path, 0.0).unwrap();
+        let slot = source.seek_slot().unwrap();
+        assert_eq!(source.next(), Some(0.1));
+        source.next();
+
+        slot.prepare(&file_path, 1.5).unwrap();
+        source.seek(1.5).unwrap();
+        assert_eq!(source.cursor().frames(), 72000);
+        assert_eq!(source.next(), Some(0.4));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
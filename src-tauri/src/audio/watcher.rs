@@ -0,0 +1,105 @@
+use cpal::traits::{DeviceTrait, HostTrait};
+use serde::Serialize;
+use std::{thread, time::Duration};
+use tracing::{info, warn};
+use crate::audio::{AudioCommand, INPUT_BUS, VIRTUAL_ROUTE, emit_event, get_audio_engine, get_audio_manager, save_input_settings, save_output_routes};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DevicesChangedEvent {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    // buses that moved back onto their saved device
+    pub rebound: Vec<String>,
+}
+
+struct DeviceSnapshot {
+    outputs: Vec<String>,
+    inputs: Vec<String>,
+}
+
+// None when the host couldn't enumerate, a failed listing shouldn't look like every device was unplugged
+fn snapshot() -> Option<DeviceSnapshot> {
+    let host = cpal::default_host();
+    let outputs = host.output_devices().ok()?.filter_map(|d| d.name().ok()).collect();
+    let inputs = host.input_devices().ok()?.filter_map(|d| d.name().ok()).collect();
+    Some(DeviceSnapshot { outputs, inputs })
+}
+
+fn diff(before: &[String], after: &[String]) -> Vec<String> {
+    after.iter().filter(|name| !before.contains(name)).cloned().collect()
+}
+
+// polls the host for device changes, cpal has no hotplug callbacks
+pub fn start_device_watcher() {
+    thread::spawn(|| {
+        let mut previous = snapshot();
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let Some(current) = snapshot() else { continue };
+            if let Some(previous) = &previous {
+                handle_changes(previous, &current);
+            }
+            previous = Some(current);
+        }
+    });
+}
+
+fn handle_changes(previous: &DeviceSnapshot, current: &DeviceSnapshot) {
+    let outputs_added = diff(&previous.outputs, &current.outputs);
+    let outputs_removed = diff(&current.outputs, &previous.outputs);
+    let inputs_added = diff(&previous.inputs, &current.inputs);
+    let inputs_removed = diff(&current.inputs, &previous.inputs);
+    if outputs_added.is_empty() && outputs_removed.is_empty() && inputs_added.is_empty() && inputs_removed.is_empty() {
+        return;
+    }
+    info!("Audio devices changed: +{:?} -{:?} (inputs +{:?} -{:?})", outputs_added, outputs_removed, inputs_added, inputs_removed);
+
+    // a device that came back under a name we still have a mixer for was restarted in between polls
+    for device_name in outputs_removed.iter().chain(&outputs_added) {
+        get_audio_engine().send_command(AudioCommand::ResetDevice { device_name: device_name.clone() });
+    }
+
+    let manager = get_audio_manager();
+    let rebound = manager.rebind_missing_devices(&outputs_added, &inputs_added);
+    if rebound.iter().any(|bus| bus != INPUT_BUS) {
+        if let Err(e) = save_output_routes() {
+            warn!("Failed to save re-bound routes: {}", e);
+        }
+    }
+    if rebound.iter().any(|bus| bus == INPUT_BUS) {
+        if let Err(e) = save_input_settings() {
+            warn!("Failed to save re-bound input device: {}", e);
+        }
+    }
+
+    // capture streams die with their device, restart once both ends are back
+    if manager.is_input_capturing() {
+        let input_name = manager.get_input_device().and_then(|d| d.name().ok());
+        let virtual_name = manager.get_route(VIRTUAL_ROUTE).and_then(|route| route.device);
+        let input_back = input_name.is_some_and(|name| inputs_added.contains(&name));
+        let virtual_back = virtual_name.is_some_and(|name| outputs_added.contains(&name));
+        if input_back || virtual_back || rebound.iter().any(|bus| bus == INPUT_BUS || bus == VIRTUAL_ROUTE) {
+            info!("Restarting input capture after device change");
+            if let Err(e) = manager.start_input_capture() {
+                warn!("Failed to restart input capture: {}", e);
+            }
+        }
+    }
+
+    emit_event("audio-devices-changed", DevicesChangedEvent {
+        added: merge(outputs_added, inputs_added),
+        removed: merge(outputs_removed, inputs_removed),
+        rebound,
+    });
+}
+
+fn merge(mut outputs: Vec<String>, inputs: Vec<String>) -> Vec<String> {
+    for name in inputs {
+        if !outputs.contains(&name) {
+            outputs.push(name);
+        }
+    }
+    outputs
+}
@@ -6,6 +6,7 @@ use std::path::Path;
 use tracing::info;
 use serde_json;
 use crate::hotkeys::Hotkey;
+use crate::audio::{Effect, LoopMode, PlayMode};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Sound {
@@ -18,10 +19,49 @@ pub struct Sound {
     pub volume: f32,
     pub start_position: Option<f32>,
     pub duration: Option<f32>,
+    pub play_mode: PlayMode,
+    pub loop_mode: LoopMode,
+    pub loop_start: Option<f32>,
+    pub loop_end: Option<f32>,
+    pub loop_count: Option<u32>,
+    pub end_position: Option<f32>,
+    pub fade_in_ms: u32,
+    pub fade_out_ms: u32,
+    pub choke_group: Option<String>,
+    pub priority: i32,
+    pub integrated_loudness: Option<f32>,
+    pub true_peak: Option<f32>,
+    // routes the sound plays on, None plays on all of them
+    pub output_routes: Option<Vec<String>>,
+    // decoded into the sample cache at startup, whatever its length
+    pub preload: bool,
+    // applied in order, see audio::Effect
+    pub effects: Vec<Effect>,
+    // -1.0 is hard left, 1.0 hard right
+    pub pan: f32,
+    // never picks the same file twice in a row when the sound has variants
+    pub no_repeat: bool,
+    // random spread applied on every trigger, in semitones and dB either way
+    pub pitch_jitter: f32,
+    pub volume_jitter_db: f32,
+    // chance of the sound's own file against its variants, 0 leaves it out
+    pub own_weight: f32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+// another file a sound can play instead of its own, picked at random on each trigger
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoundVariant {
+    pub id: String,
+    pub sound_id: String,
+    pub file_path: String,
+    // relative to the other variants and the sound's own_weight
+    pub weight: f32,
+    pub integrated_loudness: Option<f32>,
+    pub created_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Category {
     pub id: String,
@@ -48,8 +88,28 @@ pub fn init_database(db_path: &Path) -> Result<()> {
             volume REAL DEFAULT 1.0,
             start_position REAL,
             duration REAL,
+            play_mode TEXT DEFAULT 'restart',
             created_at TEXT NOT NULL,
-            updated_at TEXT NOT NULL
+            updated_at TEXT NOT NULL,
+            loop_mode TEXT DEFAULT 'off',
+            loop_start REAL,
+            loop_end REAL,
+            loop_count INTEGER,
+            end_position REAL,
+            fade_in_ms INTEGER DEFAULT 0,
+            fade_out_ms INTEGER DEFAULT 0,
+            choke_group TEXT,
+            priority INTEGER DEFAULT 0,
+            integrated_loudness REAL,
+            true_peak REAL,
+            output_routes TEXT,
+            preload INTEGER DEFAULT 0,
+            effects TEXT,
+            pan REAL DEFAULT 0,
+            no_repeat INTEGER DEFAULT 0,
+            pitch_jitter REAL DEFAULT 0,
+            volume_jitter_db REAL DEFAULT 0,
+            own_weight REAL DEFAULT 1
         )",
         [],
     )?;
@@ -66,6 +126,54 @@ pub fn init_database(db_path: &Path) -> Result<()> {
     if !columns.iter().any(|c| c == "display_name") {
         let _ = conn.execute("ALTER TABLE sounds ADD COLUMN display_name TEXT", []);
     }
+    if !columns.iter().any(|c| c == "play_mode") {
+        let _ = conn.execute("ALTER TABLE sounds ADD COLUMN play_mode TEXT DEFAULT 'restart'", []);
+    }
+    if !columns.iter().any(|c| c == "loop_mode") {
+        let _ = conn.execute("ALTER TABLE sounds ADD COLUMN loop_mode TEXT DEFAULT 'off'", []);
+        let _ = conn.execute("ALTER TABLE sounds ADD COLUMN loop_start REAL", []);
+        let _ = conn.execute("ALTER TABLE sounds ADD COLUMN loop_end REAL", []);
+        let _ = conn.execute("ALTER TABLE sounds ADD COLUMN loop_count INTEGER", []);
+    }
+    if !columns.iter().any(|c| c == "end_position") {
+        let _ = conn.execute("ALTER TABLE sounds ADD COLUMN end_position REAL", []);
+        let _ = conn.execute("ALTER TABLE sounds ADD COLUMN fade_in_ms INTEGER DEFAULT 0", []);
+        let _ = conn.execute("ALTER TABLE sounds ADD COLUMN fade_out_ms INTEGER DEFAULT 0", []);
+    }
+    if !columns.iter().any(|c| c == "choke_group") {
+        let _ = conn.execute("ALTER TABLE sounds ADD COLUMN choke_group TEXT", []);
+    }
+    if !columns.iter().any(|c| c == "priority") {
+        let _ = conn.execute("ALTER TABLE sounds ADD COLUMN priority INTEGER DEFAULT 0", []);
+    }
+    if !columns.iter().any(|c| c == "integrated_loudness") {
+        let _ = conn.execute("ALTER TABLE sounds ADD COLUMN integrated_loudness REAL", []);
+        let _ = conn.execute("ALTER TABLE sounds ADD COLUMN true_peak REAL", []);
+    }
+    if !columns.iter().any(|c| c == "output_routes") {
+        let _ = conn.execute("ALTER TABLE sounds ADD COLUMN output_routes TEXT", []);
+    }
+    if !columns.iter().any(|c| c == "preload") {
+        let _ = conn.execute("ALTER TABLE sounds ADD COLUMN preload INTEGER DEFAULT 0", []);
+    }
+    if !columns.iter().any(|c| c == "effects") {
+        let _ = conn.execute("ALTER TABLE sounds ADD COLUMN effects TEXT", []);
+    }
+    if !columns.iter().any(|c| c == "pan") {
+        let _ = conn.execute("ALTER TABLE sounds ADD COLUMN pan REAL DEFAULT 0", []);
+    }
+    if !columns.iter().any(|c| c == "no_repeat") {
+        let _ = conn.execute("ALTER TABLE sounds ADD COLUMN no_repeat INTEGER DEFAULT 0", []);
+    }
+    if !columns.iter().any(|c| c == "pitch_jitter") {
+        let _ = conn.execute("ALTER TABLE sounds ADD COLUMN pitch_jitter REAL DEFAULT 0", []);
+    }
+    if !columns.iter().any(|c| c == "volume_jitter_db") {
+        let _ = conn.execute("ALTER TABLE sounds ADD COLUMN volume_jitter_db REAL DEFAULT 0", []);
+    }
+    if !columns.iter().any(|c| c == "own_weight") {
+        let _ = conn.execute("ALTER TABLE sounds ADD COLUMN own_weight REAL DEFAULT 1", []);
+    }
 
     conn.execute(
         "CREATE TABLE IF NOT EXISTS categories (
@@ -101,6 +209,18 @@ pub fn init_database(db_path: &Path) -> Result<()> {
         }
     }
 
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS sound_variants (
+            id TEXT PRIMARY KEY,
+            sound_id TEXT NOT NULL,
+            file_path TEXT NOT NULL,
+            weight REAL DEFAULT 1,
+            integrated_loudness REAL,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
     conn.execute(
         "CREATE TABLE IF NOT EXISTS settings (
             key TEXT PRIMARY KEY,
@@ -148,9 +268,14 @@ pub fn add_sound(sound: &Sound) -> Result<()> {
         },
         None => None,
     };
+    let output_routes_json = match &sound.output_routes {
+        Some(routes) => Some(serde_json::to_string(routes)?),
+        None => None,
+    };
+    let effects_json = serde_json::to_string(&sound.effects)?;
     conn.execute(
-        "INSERT OR REPLACE INTO sounds (id, name, display_name, file_path, category, hotkey, volume, start_position, duration, created_at, updated_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        "INSERT OR REPLACE INTO sounds (id, name, display_name, file_path, category, hotkey, volume, start_position, duration, play_mode, created_at, updated_at, loop_mode, loop_start, loop_end, loop_count, end_position, fade_in_ms, fade_out_ms, choke_group, priority, integrated_loudness, true_peak, output_routes, preload, effects, pan, no_repeat, pitch_jitter, volume_jitter_db, own_weight)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30, ?31)",
         params![
             sound.id,
             sound.name,
@@ -161,79 +286,98 @@ pub fn add_sound(sound: &Sound) -> Result<()> {
             sound.volume,
             sound.start_position,
             sound.duration,
+            sound.play_mode.as_str(),
             sound.created_at.to_rfc3339(),
             sound.updated_at.to_rfc3339(),
+            sound.loop_mode.as_str(),
+            sound.loop_start,
+            sound.loop_end,
+            sound.loop_count,
+            sound.end_position,
+            sound.fade_in_ms,
+            sound.fade_out_ms,
+            sound.choke_group,
+            sound.priority,
+            sound.integrated_loudness,
+            sound.true_peak,
+            output_routes_json,
+            sound.preload,
+            effects_json,
+            sound.pan,
+            sound.no_repeat,
+            sound.pitch_jitter,
+            sound.volume_jitter_db,
+            sound.own_weight,
         ],
     )?;
     info!("Added sound: {}", sound.name);
     Ok(())
 }
 
+const SOUND_COLUMNS: &str = "id, name, display_name, file_path, category, hotkey, volume, start_position, duration, play_mode, created_at, updated_at, loop_mode, loop_start, loop_end, loop_count, end_position, fade_in_ms, fade_out_ms, choke_group, priority, integrated_loudness, true_peak, output_routes, preload, effects, pan, no_repeat, pitch_jitter, volume_jitter_db, own_weight";
+
+fn sound_from_row(row: &rusqlite::Row) -> rusqlite::Result<Sound> {
+    let hotkey_str: Option<String> = row.get(5)?;
+    let hotkey = match hotkey_str {
+        Some(ref s) => serde_json::from_str(s).ok(),
+        None => None,
+    };
+    let play_mode: Option<String> = row.get(9)?;
+    let loop_mode: Option<String> = row.get(12)?;
+    let output_routes: Option<String> = row.get(23)?;
+    let effects: Option<String> = row.get(25)?;
+    Ok(Sound {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        display_name: row.get(2)?,
+        file_path: row.get(3)?,
+        category: row.get(4)?,
+        hotkey,
+        volume: row.get(6)?,
+        start_position: row.get(7)?,
+        duration: row.get(8)?,
+        play_mode: PlayMode::from_db(play_mode.as_deref()),
+        loop_mode: LoopMode::from_db(loop_mode.as_deref()),
+        loop_start: row.get(13)?,
+        loop_end: row.get(14)?,
+        loop_count: row.get(15)?,
+        end_position: row.get(16)?,
+        fade_in_ms: row.get::<_, Option<u32>>(17)?.unwrap_or(0),
+        fade_out_ms: row.get::<_, Option<u32>>(18)?.unwrap_or(0),
+        choke_group: row.get(19)?,
+        priority: row.get::<_, Option<i32>>(20)?.unwrap_or(0),
+        integrated_loudness: row.get(21)?,
+        true_peak: row.get(22)?,
+        output_routes: output_routes.and_then(|s| serde_json::from_str(&s).ok()),
+        preload: row.get::<_, Option<bool>>(24)?.unwrap_or(false),
+        effects: effects.and_then(|s| serde_json::from_str(&s).ok()).unwrap_or_default(),
+        pan: row.get::<_, Option<f32>>(26)?.unwrap_or(0.0),
+        no_repeat: row.get::<_, Option<bool>>(27)?.unwrap_or(false),
+        pitch_jitter: row.get::<_, Option<f32>>(28)?.unwrap_or(0.0),
+        volume_jitter_db: row.get::<_, Option<f32>>(29)?.unwrap_or(0.0),
+        own_weight: row.get::<_, Option<f32>>(30)?.unwrap_or(1.0),
+        created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(10)?)
+            .unwrap_or_else(|_| Utc::now().into())
+            .with_timezone(&Utc),
+        updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(11)?)
+            .unwrap_or_else(|_| Utc::now().into())
+            .with_timezone(&Utc),
+    })
+}
+
 pub fn get_sounds() -> Result<Vec<Sound>> {
     let conn = get_connection()?;
-    let mut stmt = conn.prepare(
-        "SELECT id, name, display_name, file_path, category, hotkey, volume, start_position, duration, created_at, updated_at \
-         FROM sounds ORDER BY name"
-    )?;
-    let sound_iter = stmt.query_map([], |row| {
-        let hotkey_str: Option<String> = row.get(5)?;
-        let hotkey = match hotkey_str {
-            Some(ref s) => serde_json::from_str(s).ok(),
-            None => None,
-        };
-        Ok(Sound {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            display_name: row.get(2)?,
-            file_path: row.get(3)?,
-            category: row.get(4)?,
-            hotkey,
-            volume: row.get(6)?,
-            start_position: row.get(7)?,
-            duration: row.get(8)?,
-            created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(9)?)
-                .unwrap_or_else(|_| Utc::now().into())
-                .with_timezone(&Utc),
-            updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(10)?)
-                .unwrap_or_else(|_| Utc::now().into())
-                .with_timezone(&Utc),
-        })
-    })?;
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM sounds ORDER BY name", SOUND_COLUMNS))?;
+    let sound_iter = stmt.query_map([], sound_from_row)?;
     Ok(sound_iter.filter_map(|r| r.ok()).collect())
 }
 
 pub fn get_sound_by_id(id: &str) -> Result<Option<Sound>> {
     let conn = get_connection()?;
     
-    let mut stmt = conn.prepare(
-        "SELECT id, name, display_name, file_path, category, hotkey, volume, start_position, duration, created_at, updated_at 
-         FROM sounds WHERE id = ?"
-    )?;
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM sounds WHERE id = ?", SOUND_COLUMNS))?;
     
-    let mut sound_iter = stmt.query_map(params![id], |row| {
-        let hotkey_str: Option<String> = row.get(5)?;
-        let hotkey = match hotkey_str {
-            Some(ref s) => serde_json::from_str(s).ok(),
-            None => None,
-        };
-        Ok(Sound {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            display_name: row.get(2)?,
-            file_path: row.get(3)?,
-            category: row.get(4)?,
-            hotkey,
-            volume: row.get(6)?,
-            start_position: row.get(7)?,
-            duration: row.get(8)?,
-            created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(9)?)
-                .unwrap_or_else(|_| Utc::now().into())
-                .with_timezone(&Utc),
-            updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(10)?)
-                .unwrap_or_else(|_| Utc::now().into())
-                .with_timezone(&Utc),
-        })
-    })?;
+    let mut sound_iter = stmt.query_map(params![id], sound_from_row)?;
 
     Ok(sound_iter.next().transpose()?)
 }
@@ -268,6 +412,7 @@ pub fn set_sound_categories(sound_id: &str, categories: &[String]) -> Result<()>
 pub fn remove_sound(id: &str) -> Result<()> {
     let conn = get_connection()?;
     let _ = conn.execute("DELETE FROM sound_categories WHERE sound_id = ?", params![id]);
+    let _ = conn.execute("DELETE FROM sound_variants WHERE sound_id = ?", params![id]);
     let _ = conn.execute("DELETE FROM hotkey_bindings WHERE sound_id = ?", params![id]);
     conn.execute("DELETE FROM sounds WHERE id = ?", params![id])?;
     
@@ -278,12 +423,73 @@ pub fn remove_sound(id: &str) -> Result<()> {
 pub fn remove_all_sounds() -> Result<()> {
     let conn = get_connection()?;
     let _ = conn.execute("DELETE FROM sound_categories", []);
+    let _ = conn.execute("DELETE FROM sound_variants", []);
     let _ = conn.execute("DELETE FROM hotkey_bindings", []);
     conn.execute("DELETE FROM sounds", [])?;
     info!("Removed all sounds from database");
     Ok(())
 }
 
+pub fn add_sound_variant(variant: &SoundVariant) -> Result<()> {
+    let conn = get_connection()?;
+    conn.execute(
+        "INSERT OR REPLACE INTO sound_variants (id, sound_id, file_path, weight, integrated_loudness, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            variant.id,
+            variant.sound_id,
+            variant.file_path,
+            variant.weight,
+            variant.integrated_loudness,
+            variant.created_at.to_rfc3339(),
+        ],
+    )?;
+    info!("Added variant {} to sound {}", variant.file_path, variant.sound_id);
+    Ok(())
+}
+
+fn variant_from_row(row: &rusqlite::Row) -> rusqlite::Result<SoundVariant> {
+    Ok(SoundVariant {
+        id: row.get(0)?,
+        sound_id: row.get(1)?,
+        file_path: row.get(2)?,
+        weight: row.get::<_, Option<f32>>(3)?.unwrap_or(1.0),
+        integrated_loudness: row.get(4)?,
+        created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(5)?)
+            .unwrap_or_else(|_| Utc::now().into())
+            .with_timezone(&Utc),
+    })
+}
+
+pub fn get_sound_variants(sound_id: &str) -> Result<Vec<SoundVariant>> {
+    let conn = get_connection()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, sound_id, file_path, weight, integrated_loudness, created_at FROM sound_variants WHERE sound_id = ? ORDER BY created_at"
+    )?;
+    let rows = stmt.query_map(params![sound_id], variant_from_row)?;
+    let mut variants = Vec::new();
+    for variant in rows {
+        variants.push(variant?);
+    }
+    Ok(variants)
+}
+
+pub fn get_sound_variant(id: &str) -> Result<Option<SoundVariant>> {
+    let conn = get_connection()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, sound_id, file_path, weight, integrated_loudness, created_at FROM sound_variants WHERE id = ?"
+    )?;
+    let mut rows = stmt.query_map(params![id], variant_from_row)?;
+    Ok(rows.next().transpose()?)
+}
+
+pub fn remove_sound_variant(id: &str) -> Result<()> {
+    let conn = get_connection()?;
+    conn.execute("DELETE FROM sound_variants WHERE id = ?", params![id])?;
+    info!("Removed sound variant with id: {}", id);
+    Ok(())
+}
+
 //todo category
 pub fn add_category(category: &Category) -> Result<()> {
     let conn = get_connection()?;
@@ -447,4 +653,42 @@ pub fn update_sound_display_name(sound_id: &str, display_name: Option<&str>) ->
     )?;
     info!("Updated display name for sound with id: {}", sound_id);
     Ok(())
+}
+
+pub fn update_sound_loop(sound_id: &str, loop_mode: LoopMode, loop_start: Option<f32>, loop_end: Option<f32>, loop_count: Option<u32>) -> Result<()> {
+    let conn = get_connection()?;
+    conn.execute(
+        "UPDATE sounds SET loop_mode = ?, loop_start = ?, loop_end = ?, loop_count = ? WHERE id = ?",
+        params![loop_mode.as_str(), loop_start, loop_end, loop_count, sound_id],
+    )?;
+    info!("Updated loop for sound with id: {} -> {} ({:?}..{:?}, count: {:?})", sound_id, loop_mode.as_str(), loop_start, loop_end, loop_count);
+    Ok(())
+}
+
+pub fn update_sound_loudness(sound_id: &str, integrated_loudness: Option<f32>, true_peak: Option<f32>) -> Result<()> {
+    let conn = get_connection()?;
+    conn.execute(
+        "UPDATE sounds SET integrated_loudness = ?, true_peak = ? WHERE id = ?",
+        params![integrated_loudness, true_peak, sound_id],
+    )?;
+    Ok(())
+}
+
+pub fn update_variant_loudness(variant_id: &str, integrated_loudness: Option<f32>) -> Result<()> {
+    let conn = get_connection()?;
+    conn.execute(
+        "UPDATE sound_variants SET integrated_loudness = ? WHERE id = ?",
+        params![integrated_loudness, variant_id],
+    )?;
+    Ok(())
+}
+
+pub fn update_sound_play_mode(sound_id: &str, play_mode: PlayMode) -> Result<()> {
+    let conn = get_connection()?;
+    conn.execute(
+        "UPDATE sounds SET play_mode = ? WHERE id = ?",
+        params![play_mode.as_str(), sound_id],
+    )?;
+    info!("Updated play mode for sound with id: {} -> {}", sound_id, play_mode.as_str());
+    Ok(())
 } 
\ No newline at end of file
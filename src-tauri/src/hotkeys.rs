@@ -11,7 +11,38 @@ use once_cell::sync::OnceCell;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum HotkeyAction {
     PlaySound { sound_id: String },
+    // sent when the key of a PlaySound binding is let go, for hold mode sounds
+    ReleaseSound { sound_id: String },
     StopAllSounds,
+    // the mic passes only while held
+    PushToTalk,
+    ReleasePushToTalk,
+    // the mic is cut while held
+    PushToMute,
+    ReleasePushToMute,
+    ToggleMic,
+}
+
+impl HotkeyAction {
+    // what to send when the key of a held binding is let go
+    fn release(&self) -> Option<HotkeyAction> {
+        match self {
+            HotkeyAction::PlaySound { sound_id } => Some(HotkeyAction::ReleaseSound { sound_id: sound_id.clone() }),
+            HotkeyAction::PushToTalk => Some(HotkeyAction::ReleasePushToTalk),
+            HotkeyAction::PushToMute => Some(HotkeyAction::ReleasePushToMute),
+            _ => None,
+        }
+    }
+
+    // mic bindings are global, one of each kind
+    fn mic_binding_id(&self) -> Option<&'static str> {
+        match self {
+            HotkeyAction::PushToTalk | HotkeyAction::ReleasePushToTalk => Some("global_push_to_talk"),
+            HotkeyAction::PushToMute | HotkeyAction::ReleasePushToMute => Some("global_push_to_mute"),
+            HotkeyAction::ToggleMic => Some("global_toggle_mic"),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,6 +75,8 @@ pub struct HotkeyManager {
     key_map: Mutex<HashMap<(String, Modifiers), String>>,
     event_sender: mpsc::Sender<HotkeyAction>,
     modifier_state: Mutex<Modifiers>,
+    // keys currently down that fired a binding, so auto-repeat doesn't retrigger and release can be matched
+    held_keys: Mutex<HashMap<String, String>>,
 }
 
 impl HotkeyManager {
@@ -53,6 +86,7 @@ impl HotkeyManager {
             key_map: Mutex::new(HashMap::new()),
             event_sender,
             modifier_state: Mutex::new(Modifiers::default()),
+            held_keys: Mutex::new(HashMap::new()),
         }
     }
 
@@ -66,8 +100,9 @@ impl HotkeyManager {
 
     fn add_binding_with_persist(&self, key: String, modifiers: Modifiers, action: HotkeyAction, sound_id: Option<String>, persist: bool) -> anyhow::Result<String> {
         let id = match &action {
-            HotkeyAction::PlaySound { sound_id } => format!("sound_{}", sound_id),
+            HotkeyAction::PlaySound { sound_id } | HotkeyAction::ReleaseSound { sound_id } => format!("sound_{}", sound_id),
             HotkeyAction::StopAllSounds => "global_stop".to_string(),
+            mic_action => mic_action.mic_binding_id().unwrap_or_default().to_string(),
         };
         let binding = HotkeyBinding {
             id: id.clone(),
@@ -95,6 +130,8 @@ impl HotkeyManager {
         let mut bindings = self.bindings.lock().unwrap();
         if let Some(binding) = bindings.remove(id) {
             self.key_map.lock().unwrap().remove(&(binding.key.clone(), binding.modifiers));
+            // a key held through the removal has nothing left to release
+            self.held_keys.lock().unwrap().retain(|_, held_id| held_id != id);
             if id == "global_stop" {
                 let _ = crate::database::remove_hotkey_binding(id);
             }
@@ -129,11 +166,15 @@ impl HotkeyManager {
                 let key_str = key_to_string(key);
                 
                 if !is_modifier_key(key) {
+                    if self.held_keys.lock().unwrap().contains_key(&key_str) {
+                        return;
+                    }
                     let current_modifiers = *self.modifier_state.lock().unwrap();
                     let key_map = self.key_map.lock().unwrap();
                     
-                    if let Some(binding_id) = key_map.get(&(key_str, current_modifiers)) {
+                    if let Some(binding_id) = key_map.get(&(key_str.clone(), current_modifiers)) {
                         if let Some(binding) = self.bindings.lock().unwrap().get(binding_id) {
+                            self.held_keys.lock().unwrap().insert(key_str, binding_id.clone());
                             let _ = self.event_sender.blocking_send(binding.action.clone());
                         }
                     }
@@ -141,6 +182,14 @@ impl HotkeyManager {
             }
             EventType::KeyRelease(key) => {
                 self.update_modifier_state(key, false);
+
+                let held = self.held_keys.lock().unwrap().remove(&key_to_string(key));
+                if let Some(binding_id) = held {
+                    let release = self.bindings.lock().unwrap().get(&binding_id).and_then(|binding| binding.action.release());
+                    if let Some(action) = release {
+                        let _ = self.event_sender.blocking_send(action);
+                    }
+                }
             }
             _ => {}
         }
@@ -227,7 +276,13 @@ impl From<HotkeyBinding> for FrontendHotkeyBinding {
         let hotkey_string = hotkey_to_string(&binding.key, &binding.modifiers);
         let action_string = match binding.action {
             HotkeyAction::PlaySound { .. } => "PlaySound".to_string(),
+            HotkeyAction::ReleaseSound { .. } => "ReleaseSound".to_string(),
             HotkeyAction::StopAllSounds => "StopAllSounds".to_string(),
+            HotkeyAction::PushToTalk => "PushToTalk".to_string(),
+            HotkeyAction::ReleasePushToTalk => "ReleasePushToTalk".to_string(),
+            HotkeyAction::PushToMute => "PushToMute".to_string(),
+            HotkeyAction::ReleasePushToMute => "ReleasePushToMute".to_string(),
+            HotkeyAction::ToggleMic => "ToggleMic".to_string(),
         };
         
         FrontendHotkeyBinding {
@@ -306,6 +361,56 @@ pub async fn unregister_global_stop_hotkey(_app: tauri::AppHandle) -> Result<(),
     }
 }
 
+// push-to-talk keeps the mic closed only while a key for it is bound. held state is reset since a key
+// held while its binding changed never sends its release
+fn sync_push_to_talk(manager: &HotkeyManager) {
+    let bound = manager.get_bindings().iter().any(|binding| matches!(binding.action, HotkeyAction::PushToTalk));
+    crate::audio::get_audio_manager().update_mic_state(|state| {
+        state.push_to_talk = bound;
+        state.talking = false;
+        state.push_muted = false;
+    });
+}
+
+#[tauri::command]
+pub async fn register_mic_hotkey(_app: AppHandle, action: HotkeyAction, key: String, modifiers: Modifiers) -> Result<(), String> {
+    let Some(binding_id) = action.mic_binding_id() else {
+        return Err("Not a mic hotkey action".into());
+    };
+    if let Some(manager) = HOTKEY_MANAGER.get() {
+        manager.remove_binding(binding_id);
+        manager.add_binding(key.clone(), modifiers, action.clone(), None).map_err(|e| e.to_string())?;
+        let binding = HotkeyBinding {
+            id: binding_id.to_string(),
+            key,
+            modifiers,
+            action,
+            sound_id: None,
+            created_at: Utc::now(),
+        };
+        crate::database::save_hotkey_binding(&binding).map_err(|e| e.to_string())?;
+        sync_push_to_talk(manager);
+        Ok(())
+    } else {
+        Err("Hotkey manager not initialized".into())
+    }
+}
+
+#[tauri::command]
+pub async fn unregister_mic_hotkey(_app: AppHandle, action: HotkeyAction) -> Result<(), String> {
+    let Some(binding_id) = action.mic_binding_id() else {
+        return Err("Not a mic hotkey action".into());
+    };
+    if let Some(manager) = HOTKEY_MANAGER.get() {
+        manager.remove_binding(binding_id);
+        let _ = crate::database::remove_hotkey_binding(binding_id);
+        sync_push_to_talk(manager);
+        Ok(())
+    } else {
+        Err("Hotkey manager not initialized".into())
+    }
+}
+
 pub fn init_hotkeys() -> mpsc::Receiver<HotkeyAction> {
     let (tx, rx) = mpsc::channel(100);
     let manager = Arc::new(HotkeyManager::new(tx));
@@ -347,6 +452,7 @@ pub fn init_hotkeys() -> mpsc::Receiver<HotkeyAction> {
         }
     }
     
+    sync_push_to_talk(&manager);
     manager.clone().start_listening();
     HOTKEY_MANAGER.set(manager).unwrap();
     rx
@@ -30,6 +30,18 @@ fn main() {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
+    // headless render for machines without audio hardware, writes one wav per device next to the script
+    if let Some(script) = std::env::args().find_map(|a| a.strip_prefix("--render=").map(str::to_string)) {
+        match audio::render_script_file(std::path::Path::new(&script)) {
+            Ok(files) => tracing::info!("Wrote {:?}", files),
+            Err(e) => {
+                tracing::error!("Failed to render {}: {}", script, e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
             std::thread::spawn(|| { audio::get_audio_manager(); });
     audio::get_audio_engine();
 
@@ -50,6 +62,13 @@ fn main() {
                 .unwrap_or_else(|_| None)
                 .unwrap_or_else(|| String::new());
             external::youtube::init_youtube_service(youtube_api_key)?;
+            soundboard::load_voice_limit();
+            soundboard::load_sample_cache_config();
+            soundboard::warm_sample_cache();
+
+            audio::set_event_handle(app.handle().clone());
+            audio::load_audio_settings();
+            audio::start_device_watcher();
 
             let mut event_receiver = init_hotkeys();
 
@@ -65,13 +84,42 @@ fn main() {
                     if let Some(action) = event_receiver.blocking_recv() {
                         match action {
                             HotkeyAction::PlaySound { sound_id } => {
-                                let _ = crate::soundboard::play_sound(sound_id.clone(), app_handle.state::<std::sync::Mutex<audio::AudioManager>>());
+                                let state = app_handle.state::<std::sync::Mutex<audio::AudioManager>>();
+                                if let Err(e) = crate::soundboard::start_sound(&sound_id, &state.lock().unwrap()) {
+                                    tracing::error!("Hotkey could not play {}: {}", sound_id, e);
+                                }
                                 let _ = app_handle.emit("hotkey-play-sound", sound_id);
                             }
+                            HotkeyAction::ReleaseSound { sound_id } => {
+                                let hold = database::get_sound_by_id(&sound_id)
+                                    .ok()
+                                    .flatten()
+                                    .map(|sound| sound.play_mode == audio::PlayMode::Hold)
+                                    .unwrap_or(false);
+                                if hold {
+                                    audio::get_audio_engine().send_command(audio::AudioCommand::Stop { sound_id: sound_id.clone() });
+                                    let _ = app_handle.emit("hotkey-release-sound", sound_id);
+                                }
+                            }
                             HotkeyAction::StopAllSounds => {
                                 let _ = crate::soundboard::stop_all_sounds();
                                 let _ = app_handle.emit("hotkey-stop-all-sounds", ());
                             }
+                            HotkeyAction::PushToTalk => {
+                                audio::get_audio_manager().update_mic_state(|state| state.talking = true);
+                            }
+                            HotkeyAction::ReleasePushToTalk => {
+                                audio::get_audio_manager().update_mic_state(|state| state.talking = false);
+                            }
+                            HotkeyAction::PushToMute => {
+                                audio::get_audio_manager().update_mic_state(|state| state.push_muted = true);
+                            }
+                            HotkeyAction::ReleasePushToMute => {
+                                audio::get_audio_manager().update_mic_state(|state| state.push_muted = false);
+                            }
+                            HotkeyAction::ToggleMic => {
+                                audio::get_audio_manager().update_mic_state(|state| state.toggled_off = !state.toggled_off);
+                            }
                         }
                     }
                     std::thread::sleep(std::time::Duration::from_millis(10));
@@ -85,23 +133,48 @@ fn main() {
             audio::set_virtual_device,
             audio::set_output_device,
             audio::set_input_device,
+            audio::get_selected_devices,
+            audio::get_missing_devices,
+            audio::get_sample_cache_stats,
+            audio::clear_sample_cache,
             audio::get_virtual_volume,
             audio::set_virtual_volume,
             audio::get_output_volume,
             audio::set_output_volume,
+            audio::get_virtual_limiter,
+            audio::set_virtual_limiter,
+            audio::get_output_limiter,
+            audio::set_output_limiter,
             audio::get_input_volume,
             audio::set_input_volume,
+            audio::get_mic_processing,
+            audio::set_mic_noise_gate,
+            audio::set_mic_auto_gain,
+            audio::set_mic_compressor,
+            audio::get_mic_state,
+            audio::get_ducking,
+            audio::set_ducking,
             audio::start_input_capture,
             audio::stop_input_capture,
             audio::list_all_devices,
             audio::get_audio_status,
             audio::play_audio_file_command,
             audio::stop_sound_command,
+            audio::stop_voice_command,
             audio::stop_all_sounds_command,
+            audio::pause_sound_command,
+            audio::resume_sound_command,
+            audio::pause_all_sounds_command,
+            audio::resume_all_sounds_command,
+            audio::seek_sound_command,
             audio::update_sound_volume_command,
             audio::update_device_volumes_command,
             audio::get_playing_sounds_command,
+            audio::get_playing_sounds_info_command,
+            audio::get_paused_sounds_command,
             audio::get_playback_position,
+            audio::get_progress_interval,
+            audio::set_progress_interval,
             audio::restart_sound_from_position,
             soundboard::get_sounds,
             soundboard::add_sound,
@@ -110,6 +183,10 @@ fn main() {
             soundboard::play_sound,
             soundboard::stop_sound,
             soundboard::stop_all_sounds,
+            soundboard::pause_sound,
+            soundboard::resume_sound,
+            soundboard::pause_all_sounds,
+            soundboard::resume_all_sounds,
             soundboard::get_categories,
             soundboard::add_category,
             soundboard::remove_category,
@@ -117,18 +194,49 @@ fn main() {
             soundboard::update_sound_volume,
             soundboard::update_sound_hotkey,
             soundboard::update_sound_display_name,
+            soundboard::update_sound_play_mode,
+            soundboard::update_sound_loop,
+            soundboard::update_sound_choke_group,
+            soundboard::update_sound_priority,
+            soundboard::update_sound_output_routes,
+            soundboard::update_sound_preload,
+            soundboard::update_sound_effects,
+            soundboard::update_sound_pan,
+            soundboard::update_sound_variation,
+            soundboard::get_sound_variants,
+            soundboard::add_sound_variant,
+            soundboard::update_sound_own_weight,
+            soundboard::update_sound_variant_weight,
+            soundboard::remove_sound_variant,
+            soundboard::get_sample_cache_config,
+            soundboard::set_sample_cache_config,
+            soundboard::get_output_routes,
+            soundboard::set_output_route,
+            soundboard::remove_output_route,
+            soundboard::set_output_route_muted,
+            soundboard::set_output_route_channel_mode,
             soundboard::update_sound_category,
             soundboard::update_sound_categories,
             soundboard::play_sound_local,
             soundboard::update_sound_start_position,
+            soundboard::update_sound_trim,
             soundboard::get_playing_sounds,
+            soundboard::get_playing_sounds_info,
+            soundboard::get_paused_sounds,
             soundboard::seek_sound,
+            soundboard::get_voice_limit,
+            soundboard::set_voice_limit,
+            soundboard::get_loudness_target,
+            soundboard::set_loudness_target,
+            soundboard::analyze_library,
             hotkeys::register_hotkey,
             hotkeys::unregister_hotkey,
             hotkeys::update_hotkey,
             hotkeys::get_hotkey_bindings,
             hotkeys::register_global_stop_hotkey,
             hotkeys::unregister_global_stop_hotkey,
+            hotkeys::register_mic_hotkey,
+            hotkeys::unregister_mic_hotkey,
             app_handlers::get_app_data_dir,
             app_handlers::create_directory,
             app_handlers::save_setting,
@@ -1,8 +1,11 @@
 use crate::database;
-use crate::hotkeys::Hotkey;
+use crate::hotkeys::{Hotkey, HotkeyAction};
 use crate::audio;
 use anyhow::Result;
+use once_cell::sync::OnceCell;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Mutex};
 use tracing::info;
 use uuid::Uuid;
 
@@ -13,6 +16,7 @@ pub struct AddSoundRequest {
     pub category: Option<String>,
     pub hotkey: Option<Hotkey>,
     pub volume: Option<f32>,
+    pub play_mode: Option<audio::PlayMode>,
 }
 
 #[derive(Debug, Serialize)]
@@ -26,6 +30,27 @@ pub struct SoundResponse {
     pub volume: f32,
     pub start_position: Option<f32>,
     pub duration: Option<f32>,
+    pub play_mode: audio::PlayMode,
+    pub loop_mode: audio::LoopMode,
+    pub loop_start: Option<f32>,
+    pub loop_end: Option<f32>,
+    pub loop_count: Option<u32>,
+    pub end_position: Option<f32>,
+    pub fade_in_ms: u32,
+    pub fade_out_ms: u32,
+    pub choke_group: Option<String>,
+    pub priority: i32,
+    pub integrated_loudness: Option<f32>,
+    pub true_peak: Option<f32>,
+    pub output_routes: Option<Vec<String>>,
+    pub preload: bool,
+    pub effects: Vec<audio::Effect>,
+    pub pan: f32,
+    pub no_repeat: bool,
+    pub pitch_jitter: f32,
+    pub volume_jitter_db: f32,
+    pub own_weight: f32,
+    pub variants: Vec<database::SoundVariant>,
     pub created_at: String,
     pub updated_at: String,
     pub categories: Vec<String>,
@@ -34,6 +59,7 @@ pub struct SoundResponse {
 impl From<database::Sound> for SoundResponse {
     fn from(sound: database::Sound) -> Self {
         let categories = database::get_sound_categories(&sound.id).unwrap_or_default();
+        let variants = database::get_sound_variants(&sound.id).unwrap_or_default();
         Self {
             id: sound.id,
             name: sound.name,
@@ -44,6 +70,27 @@ impl From<database::Sound> for SoundResponse {
             volume: sound.volume,
             start_position: sound.start_position,
             duration: sound.duration,
+            play_mode: sound.play_mode,
+            loop_mode: sound.loop_mode,
+            loop_start: sound.loop_start,
+            loop_end: sound.loop_end,
+            loop_count: sound.loop_count,
+            end_position: sound.end_position,
+            fade_in_ms: sound.fade_in_ms,
+            fade_out_ms: sound.fade_out_ms,
+            choke_group: sound.choke_group,
+            priority: sound.priority,
+            integrated_loudness: sound.integrated_loudness,
+            true_peak: sound.true_peak,
+            output_routes: sound.output_routes,
+            preload: sound.preload,
+            effects: sound.effects,
+            pan: sound.pan,
+            no_repeat: sound.no_repeat,
+            pitch_jitter: sound.pitch_jitter,
+            volume_jitter_db: sound.volume_jitter_db,
+            own_weight: sound.own_weight,
+            variants,
             created_at: sound.created_at.to_rfc3339(),
             updated_at: sound.updated_at.to_rfc3339(),
             categories,
@@ -51,6 +98,153 @@ impl From<database::Sound> for SoundResponse {
     }
 }
 
+fn loop_region(sound: &database::Sound) -> Option<audio::LoopRegion> {
+    let start = match sound.loop_mode {
+        audio::LoopMode::Off => return None,
+        audio::LoopMode::File => sound.start_position.unwrap_or(0.0),
+        audio::LoopMode::Region => sound.loop_start.or(sound.start_position).unwrap_or(0.0),
+    };
+    let end = match sound.loop_mode {
+        audio::LoopMode::Region => sound.loop_end.or(sound.end_position),
+        _ => sound.end_position,
+    };
+    Some(audio::LoopRegion { start, end, repeats: sound.loop_count })
+}
+
+fn loudness_target() -> Option<f32> {
+    database::get_setting("loudness_target")
+        .ok()
+        .flatten()
+        .and_then(|value| value.parse::<f32>().ok())
+}
+
+fn normalization_gain(sound: &database::Sound) -> f32 {
+    gain_for_loudness(sound.integrated_loudness)
+}
+
+fn gain_for_loudness(integrated_loudness: Option<f32>) -> f32 {
+    match (loudness_target(), integrated_loudness) {
+        (Some(target), Some(loudness)) => audio::db_to_gain(audio::normalization_gain_db(loudness, target)),
+        _ => 1.0,
+    }
+}
+
+// the variant each sound played last, None being the sound's own file
+static LAST_VARIANTS: OnceCell<Mutex<HashMap<String, Option<String>>>> = OnceCell::new();
+
+// a sound with variants plays either its own file or one of them, picked by weight
+fn pick_variant(sound: &database::Sound) -> Option<database::SoundVariant> {
+    let variants = database::get_sound_variants(&sound.id).unwrap_or_default();
+    if variants.is_empty() {
+        return None;
+    }
+    let mut last_variants = LAST_VARIANTS.get_or_init(|| Mutex::new(HashMap::new())).lock().unwrap();
+    let previous = last_variants.get(&sound.id).cloned();
+
+    let mut candidates: Vec<(Option<&database::SoundVariant>, f32)> = std::iter::once((None, sound.own_weight.max(0.0)))
+        .chain(variants.iter().map(|variant| (Some(variant), variant.weight.max(0.0))))
+        .collect();
+    if sound.no_repeat {
+        candidates.retain(|(variant, _)| previous.as_ref() != Some(&variant.map(|v| v.id.clone())));
+    }
+    let total: f32 = candidates.iter().map(|(_, weight)| weight).sum();
+    let picked = if total <= 0.0 {
+        candidates.first()
+    } else {
+        let mut roll = rand::thread_rng().gen_range(0.0..total);
+        candidates
+            .iter()
+            .find(|(_, weight)| {
+                if roll < *weight {
+                    return true;
+                }
+                roll -= weight;
+                false
+            })
+            .or(candidates.last())
+    };
+
+    let chosen = picked.and_then(|(variant, _)| *variant).cloned();
+    last_variants.insert(sound.id.clone(), chosen.as_ref().map(|variant| variant.id.clone()));
+    chosen
+}
+
+// the variant a sound played last, so a seek after it finished restarts the same file
+fn last_variant(sound_id: &str) -> Option<database::SoundVariant> {
+    let variant_id = LAST_VARIANTS.get()?.lock().unwrap().get(sound_id).cloned().flatten()?;
+    database::get_sound_variant(&variant_id).ok().flatten()
+}
+
+// a variant plays whole, the trim and loop points belong to the sound's own file
+fn variant_playback(variant: database::SoundVariant, mut options: audio::PlayOptions) -> (String, audio::PlayOptions) {
+    options.start_position = None;
+    options.trim.end_position = None;
+    options.loop_region = options.loop_region.map(|region| audio::LoopRegion { start: 0.0, end: None, repeats: region.repeats });
+    options.normalization_gain = gain_for_loudness(variant.integrated_loudness);
+    (variant.file_path, options)
+}
+
+// picks the file for this trigger and applies the sound's jitter
+fn prepare_playback(sound: &database::Sound, local_only: bool) -> (String, audio::PlayOptions) {
+    let (file_path, mut options) = match pick_variant(sound) {
+        Some(variant) => variant_playback(variant, play_options(sound, local_only)),
+        None => (sound.file_path.clone(), play_options(sound, local_only)),
+    };
+
+    let mut rng = rand::thread_rng();
+    if sound.pitch_jitter > 0.0 {
+        let semitones = rng.gen_range(-sound.pitch_jitter..=sound.pitch_jitter);
+        options.jitter_rate = Some(2f32.powf(semitones / 12.0));
+    }
+    if sound.volume_jitter_db > 0.0 {
+        let db = rng.gen_range(-sound.volume_jitter_db..=sound.volume_jitter_db);
+        options.sound_volume *= audio::db_to_gain(db);
+    }
+    (file_path, options)
+}
+
+// decoding a whole file takes a while, keep it off the async runtime
+async fn analyze_file_loudness(file_path: String) -> Option<audio::LoudnessAnalysis> {
+    let path = file_path.clone();
+    match tauri::async_runtime::spawn_blocking(move || audio::analyze_loudness(&path)).await {
+        Ok(Ok(analysis)) => {
+            info!("Analyzed loudness of {}: {:?} LUFS, {:.1} dBTP", file_path, analysis.integrated_lufs, analysis.true_peak_db);
+            Some(analysis)
+        }
+        Ok(Err(e)) => {
+            tracing::warn!("Failed to analyze loudness of {}: {}", file_path, e);
+            None
+        }
+        Err(e) => {
+            tracing::warn!("Loudness analysis task failed for {}: {}", file_path, e);
+            None
+        }
+    }
+}
+
+fn play_options(sound: &database::Sound, local_only: bool) -> audio::PlayOptions {
+    audio::PlayOptions {
+        start_position: sound.start_position,
+        sound_volume: sound.volume,
+        normalization_gain: normalization_gain(sound),
+        local_only,
+        play_mode: sound.play_mode,
+        loop_region: loop_region(sound),
+        trim: audio::Trim {
+            end_position: sound.end_position,
+            fade_in_ms: sound.fade_in_ms,
+            fade_out_ms: sound.fade_out_ms,
+        },
+        choke_group: sound.choke_group.clone(),
+        priority: sound.priority,
+        output_routes: sound.output_routes.clone(),
+        effects: sound.effects.clone(),
+        jitter_rate: None,
+        pan: sound.pan,
+        categories: database::get_sound_categories(&sound.id).unwrap_or_default(),
+    }
+}
+
 #[tauri::command]
 pub async fn get_sounds() -> Result<Vec<SoundResponse>, String> {
     let sounds = database::get_sounds().map_err(|e| e.to_string())?;
@@ -63,6 +257,7 @@ pub async fn add_sound(request: AddSoundRequest) -> Result<SoundResponse, String
     
     let duration = crate::audio::get_audio_duration(&request.file_path)
         .map_err(|e| format!("Failed to get audio duration: {}", e))?;
+    let loudness = analyze_file_loudness(request.file_path.clone()).await;
 
     let now = chrono::Utc::now();
     let sound = database::Sound {
@@ -75,6 +270,26 @@ pub async fn add_sound(request: AddSoundRequest) -> Result<SoundResponse, String
         volume: request.volume.unwrap_or(1.0).clamp(0.0, 1.0),
         start_position: None,
         duration: Some(duration),
+        play_mode: request.play_mode.unwrap_or_default(),
+        loop_mode: audio::LoopMode::Off,
+        loop_start: None,
+        loop_end: None,
+        loop_count: None,
+        end_position: None,
+        fade_in_ms: 0,
+        fade_out_ms: 0,
+        choke_group: None,
+        priority: 0,
+        integrated_loudness: loudness.and_then(|l| l.integrated_lufs),
+        true_peak: loudness.map(|l| l.true_peak_db).filter(|peak| peak.is_finite()),
+        output_routes: None,
+        preload: false,
+        effects: Vec::new(),
+        pan: 0.0,
+        no_repeat: false,
+        pitch_jitter: 0.0,
+        volume_jitter_db: 0.0,
+        own_weight: 1.0,
         created_at: now,
         updated_at: now,
     };
@@ -110,20 +325,27 @@ pub async fn remove_sound(id: String, delete_file: Option<bool>) -> Result<(), S
 
 #[tauri::command]
 pub async fn play_sound(id: String, state: tauri::State<'_, std::sync::Mutex<audio::AudioManager>>) -> Result<(), String> {
-    let sound = database::get_sound_by_id(&id)
+    start_sound(&id, &state.lock().unwrap())
+}
+
+// queues the sound on the engine before returning, so the hotkey dispatcher's release (a Stop for hold
+// mode) always lands after its play
+pub fn start_sound(id: &str, manager: &audio::AudioManager) -> Result<(), String> {
+    let sound = database::get_sound_by_id(id)
         .map_err(|e| e.to_string())?
         .ok_or("Sound not found")?;
 
-    let _ = crate::audio::stop_sound_command(id.clone()).await;
+    let (file_path, options) = prepare_playback(&sound, false);
+    let start_position = options.start_position.unwrap_or(0.0);
+    audio::get_audio_engine().send_command(audio::AudioCommand::Play {
+        file_path: file_path.clone(),
+        sound_id: id.to_string(),
+        options,
+    });
 
-    crate::audio::play_audio_file_command(sound.file_path.clone(), id.clone(), sound.start_position, sound.volume, Some(false))
-        .await
-        .map_err(|e| e.to_string())?;
-
-    let start_position = sound.start_position.unwrap_or(0.0);
-    state.lock().unwrap().set_playback_position(&id, start_position);
+    manager.set_playback_position(id, start_position);
 
-    info!("Playing sound: {} (start: {:?}, volume: {})", sound.name, sound.start_position, sound.volume);
+    info!("Playing sound: {} from {} (start: {:?}, volume: {})", sound.name, file_path, sound.start_position, sound.volume);
     Ok(())
 }
 
@@ -143,6 +365,34 @@ pub async fn stop_all_sounds() -> Result<(), String> {
     Ok(())
 }
 
+#[tauri::command]
+pub async fn pause_sound(id: String) -> Result<(), String> {
+    crate::audio::pause_sound_command(id.clone()).await?;
+    info!("Paused sound with id: {}", id);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn resume_sound(id: String) -> Result<(), String> {
+    crate::audio::resume_sound_command(id.clone()).await?;
+    info!("Resumed sound with id: {}", id);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn pause_all_sounds() -> Result<(), String> {
+    crate::audio::pause_all_sounds_command().await?;
+    info!("Paused all sounds");
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn resume_all_sounds() -> Result<(), String> {
+    crate::audio::resume_all_sounds_command().await?;
+    info!("Resumed all sounds");
+    Ok(())
+}
+
 
 #[tauri::command]
 pub async fn get_categories() -> Result<Vec<database::Category>, String> {
@@ -197,6 +447,7 @@ pub async fn import_audio_file(file_path: String) -> Result<SoundResponse, Strin
         category: None,
         hotkey: None,
         volume: Some(1.0),
+        play_mode: None,
     };
 
     info!("Calling add_sound with request: {:?}", request);
@@ -253,6 +504,207 @@ pub async fn update_sound_display_name(id: String, display_name: Option<String>)
     Ok(())
 }
 
+#[tauri::command]
+pub async fn update_sound_play_mode(id: String, play_mode: audio::PlayMode) -> Result<(), String> {
+    database::update_sound_play_mode(&id, play_mode)
+        .map_err(|e| e.to_string())?;
+    info!("Updated play mode for sound: {} -> {}", id, play_mode.as_str());
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn update_sound_loop(id: String, loop_mode: audio::LoopMode, loop_start: Option<f32>, loop_end: Option<f32>, loop_count: Option<u32>) -> Result<(), String> {
+    if let (Some(start), Some(end)) = (loop_start, loop_end) {
+        if end <= start {
+            return Err("Loop end must be after loop start".into());
+        }
+    }
+    database::update_sound_loop(&id, loop_mode, loop_start.map(|s| s.max(0.0)), loop_end, loop_count)
+        .map_err(|e| e.to_string())?;
+    info!("Updated loop for sound: {} -> {}", id, loop_mode.as_str());
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn update_sound_choke_group(id: String, choke_group: Option<String>) -> Result<(), String> {
+    let mut sound = database::get_sound_by_id(&id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Sound not found")?;
+
+    sound.choke_group = choke_group
+        .map(|group| group.trim().to_string())
+        .filter(|group| !group.is_empty());
+    sound.updated_at = chrono::Utc::now();
+
+    database::add_sound(&sound).map_err(|e| e.to_string())?;
+    info!("Updated choke group for sound: {} -> {:?}", sound.name, sound.choke_group);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn update_sound_priority(id: String, priority: i32) -> Result<(), String> {
+    let mut sound = database::get_sound_by_id(&id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Sound not found")?;
+
+    sound.priority = priority;
+    sound.updated_at = chrono::Utc::now();
+
+    database::add_sound(&sound).map_err(|e| e.to_string())?;
+    info!("Updated priority for sound: {} -> {}", sound.name, priority);
+    Ok(())
+}
+
+// None plays the sound on every route, an empty list is rejected since it would never be heard
+#[tauri::command]
+pub async fn update_sound_output_routes(id: String, output_routes: Option<Vec<String>>) -> Result<(), String> {
+    let mut sound = database::get_sound_by_id(&id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Sound not found")?;
+
+    if let Some(routes) = &output_routes {
+        if routes.is_empty() {
+            return Err("A sound needs at least one route".into());
+        }
+        let manager = audio::get_audio_manager();
+        if let Some(unknown) = routes.iter().find(|name| manager.get_route(name).is_none()) {
+            return Err(format!("Route not found: {}", unknown));
+        }
+    }
+    sound.output_routes = output_routes;
+    sound.updated_at = chrono::Utc::now();
+
+    database::add_sound(&sound).map_err(|e| e.to_string())?;
+    info!("Updated output routes for sound: {} -> {:?}", sound.name, sound.output_routes);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn update_sound_preload(id: String, preload: bool) -> Result<(), String> {
+    let mut sound = database::get_sound_by_id(&id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Sound not found")?;
+
+    sound.preload = preload;
+    sound.updated_at = chrono::Utc::now();
+
+    database::add_sound(&sound).map_err(|e| e.to_string())?;
+    if preload {
+        audio::get_sample_cache().warm(vec![(sound.file_path.clone(), true)]);
+    }
+    info!("Updated preload for sound: {} -> {}", sound.name, preload);
+    Ok(())
+}
+
+// voices of the sound that are already playing switch to the new chain straight away
+#[tauri::command]
+pub async fn update_sound_effects(id: String, effects: Vec<audio::Effect>) -> Result<(), String> {
+    let mut sound = database::get_sound_by_id(&id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Sound not found")?;
+
+    sound.effects = effects;
+    sound.updated_at = chrono::Utc::now();
+
+    database::add_sound(&sound).map_err(|e| e.to_string())?;
+    audio::get_audio_engine().send_command(audio::AudioCommand::UpdateEffects {
+        sound_id: sound.id.clone(),
+        effects: sound.effects.clone(),
+    });
+    info!("Updated effects for sound: {} ({} effects)", sound.name, sound.effects.len());
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn update_sound_pan(id: String, pan: f32) -> Result<(), String> {
+    let mut sound = database::get_sound_by_id(&id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Sound not found")?;
+
+    sound.pan = pan.clamp(-1.0, 1.0);
+    sound.updated_at = chrono::Utc::now();
+
+    database::add_sound(&sound).map_err(|e| e.to_string())?;
+    audio::get_audio_engine().send_command(audio::AudioCommand::UpdatePan {
+        sound_id: sound.id.clone(),
+        pan: sound.pan,
+    });
+    info!("Updated pan for sound: {} -> {}", sound.name, sound.pan);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn update_sound_variation(id: String, no_repeat: bool, pitch_jitter: f32, volume_jitter_db: f32) -> Result<(), String> {
+    let mut sound = database::get_sound_by_id(&id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Sound not found")?;
+
+    sound.no_repeat = no_repeat;
+    sound.pitch_jitter = pitch_jitter.clamp(0.0, 12.0);
+    sound.volume_jitter_db = volume_jitter_db.clamp(0.0, 24.0);
+    sound.updated_at = chrono::Utc::now();
+
+    database::add_sound(&sound).map_err(|e| e.to_string())?;
+    info!("Updated variation for sound: {} (no repeat: {}, pitch ±{} st, volume ±{} dB)", sound.name, sound.no_repeat, sound.pitch_jitter, sound.volume_jitter_db);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_sound_variants(sound_id: String) -> Result<Vec<database::SoundVariant>, String> {
+    database::get_sound_variants(&sound_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn add_sound_variant(sound_id: String, file_path: String, weight: Option<f32>) -> Result<database::SoundVariant, String> {
+    database::get_sound_by_id(&sound_id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Sound not found")?;
+    crate::audio::get_audio_duration(&file_path)
+        .map_err(|e| format!("Failed to get audio duration: {}", e))?;
+    let loudness = analyze_file_loudness(file_path.clone()).await;
+
+    let variant = database::SoundVariant {
+        id: Uuid::new_v4().to_string(),
+        sound_id,
+        file_path,
+        weight: weight.unwrap_or(1.0).max(0.0),
+        integrated_loudness: loudness.and_then(|l| l.integrated_lufs),
+        created_at: chrono::Utc::now(),
+    };
+    database::add_sound_variant(&variant).map_err(|e| e.to_string())?;
+    Ok(variant)
+}
+
+#[tauri::command]
+pub async fn update_sound_own_weight(id: String, weight: f32) -> Result<(), String> {
+    let mut sound = database::get_sound_by_id(&id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Sound not found")?;
+
+    sound.own_weight = weight.max(0.0);
+    sound.updated_at = chrono::Utc::now();
+    database::add_sound(&sound).map_err(|e| e.to_string())?;
+    info!("Updated own weight for sound: {} -> {}", sound.name, sound.own_weight);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn update_sound_variant_weight(id: String, weight: f32) -> Result<(), String> {
+    let mut variant = database::get_sound_variant(&id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Variant not found")?;
+
+    variant.weight = weight.max(0.0);
+    database::add_sound_variant(&variant).map_err(|e| e.to_string())?;
+    info!("Updated weight for variant {} -> {}", variant.file_path, variant.weight);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn remove_sound_variant(id: String) -> Result<(), String> {
+    database::remove_sound_variant(&id).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn update_sound_category(id: String, category: Option<String>) -> Result<(), String> {
     let mut sound = database::get_sound_by_id(&id)
@@ -289,13 +741,12 @@ pub async fn play_sound_local(id: String) -> Result<(), String> {
         .map_err(|e| e.to_string())?
         .ok_or("Sound not found")?;
 
-    let _ = crate::audio::stop_sound_command(id.clone()).await;
-
-    crate::audio::play_audio_file_command(sound.file_path, id, sound.start_position, sound.volume, Some(true))
+    let (file_path, options) = prepare_playback(&sound, true);
+    crate::audio::play_audio_file_command(file_path.clone(), id, Some(options))
         .await
         .map_err(|e| e.to_string())?;
 
-    info!("Playing sound locally: {} (start: {:?}, volume: {})", sound.name, sound.start_position, sound.volume);
+    info!("Playing sound locally: {} from {} (start: {:?}, volume: {})", sound.name, file_path, sound.start_position, sound.volume);
     Ok(())
 }
 
@@ -315,21 +766,71 @@ pub async fn update_sound_start_position(id: String, start_position: f32) -> Res
     Ok(())
 }
 
+#[tauri::command]
+pub async fn update_sound_trim(id: String, end_position: Option<f32>, fade_in_ms: u32, fade_out_ms: u32) -> Result<(), String> {
+    info!("Received trim update request for sound {}: end {:?}, fade in {}ms, fade out {}ms", id, end_position, fade_in_ms, fade_out_ms);
+
+    let mut sound = database::get_sound_by_id(&id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Sound not found")?;
+
+    if let Some(end_position) = end_position {
+        if end_position <= sound.start_position.unwrap_or(0.0) {
+            return Err("End position must be after the start position".into());
+        }
+    }
+
+    sound.end_position = end_position;
+    sound.fade_in_ms = fade_in_ms;
+    sound.fade_out_ms = fade_out_ms;
+    sound.updated_at = chrono::Utc::now();
+
+    database::add_sound(&sound).map_err(|e| e.to_string())?;
+    info!("Updated trim for sound: {}", sound.name);
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn get_playing_sounds() -> Result<Vec<String>, String> {
     let playing_sounds = crate::audio::get_playing_sounds_command().await?;
     Ok(playing_sounds)
 }
 
+#[tauri::command]
+pub async fn get_playing_sounds_info() -> Result<Vec<audio::PlayingSoundInfo>, String> {
+    crate::audio::get_playing_sounds_info_command().await
+}
+
+#[tauri::command]
+pub async fn get_paused_sounds() -> Result<Vec<String>, String> {
+    crate::audio::get_paused_sounds_command().await
+}
+
 #[tauri::command]
 pub async fn seek_sound(id: String, position: f32, local_only: bool) -> Result<(), String> {
     let sound = database::get_sound_by_id(&id)
         .map_err(|e| e.to_string())?
         .ok_or("Sound not found")?;
 
-    crate::audio::restart_sound_from_position(id, sound.file_path, position, sound.volume, local_only)
-        .await
-        .map_err(|e| e.to_string())?;
+    // live voices seek in place, anything that already finished is started again from the new position
+    if audio::get_audio_engine().get_playback_position(&id).is_some() {
+        crate::audio::seek_sound_command(id, position)
+            .await
+            .map_err(|e| e.to_string())?;
+    } else {
+        let (file_path, options) = match last_variant(&id) {
+            Some(variant) => variant_playback(variant, play_options(&sound, local_only)),
+            None => (sound.file_path.clone(), play_options(&sound, local_only)),
+        };
+        let options = audio::PlayOptions {
+            start_position: Some(position),
+            play_mode: audio::PlayMode::Restart,
+            ..options
+        };
+        crate::audio::play_audio_file_command(file_path, id, Some(options))
+            .await
+            .map_err(|e| e.to_string())?;
+    }
 
     info!("Seeking sound {} to position {} (local_only: {})", sound.name, position, local_only);
     Ok(())
@@ -341,4 +842,175 @@ pub async fn remove_all_sounds() -> Result<(), String> {
     crate::database::remove_all_sounds().map_err(|e| e.to_string())?;
     tracing::info!("Removed all sounds from database");
     Ok(())
-}
\ No newline at end of file
+}
+// voice limit is kept in settings so it survives restarts, the engine only holds the live copy
+pub fn load_voice_limit() {
+    let max_voices = database::get_setting("max_voices")
+        .ok()
+        .flatten()
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(audio::DEFAULT_MAX_VOICES);
+    let policy = database::get_setting("voice_steal_policy").ok().flatten();
+    audio::get_audio_engine().set_voice_limit(audio::VoiceLimit {
+        max_voices,
+        policy: audio::StealPolicy::from_db(policy.as_deref()),
+    });
+}
+
+#[tauri::command]
+pub async fn get_voice_limit() -> Result<audio::VoiceLimit, String> {
+    Ok(audio::get_audio_engine().get_voice_limit())
+}
+
+#[tauri::command]
+pub async fn set_voice_limit(max_voices: usize, policy: audio::StealPolicy) -> Result<(), String> {
+    if max_voices == 0 {
+        return Err("Max voices must be at least 1".into());
+    }
+    database::save_setting("max_voices", &max_voices.to_string()).map_err(|e| e.to_string())?;
+    database::save_setting("voice_steal_policy", policy.as_str()).map_err(|e| e.to_string())?;
+    audio::get_audio_engine().set_voice_limit(audio::VoiceLimit { max_voices, policy });
+    Ok(())
+}
+
+pub fn load_sample_cache_config() {
+    let defaults = audio::SampleCacheConfig::default();
+    let budget_mb = database::get_setting("sample_cache_mb")
+        .ok()
+        .flatten()
+        .and_then(|value| value.parse::<u32>().ok())
+        .unwrap_or(defaults.budget_mb);
+    let max_clip_seconds = database::get_setting("sample_cache_max_clip_seconds")
+        .ok()
+        .flatten()
+        .and_then(|value| value.parse::<f32>().ok())
+        .unwrap_or(defaults.max_clip_seconds);
+    audio::get_sample_cache().set_config(audio::SampleCacheConfig { budget_mb, max_clip_seconds });
+}
+
+// decodes preload sounds and anything bound to a hotkey so the first press is already instant
+pub fn warm_sample_cache() {
+    let sounds = match database::get_sounds() {
+        Ok(sounds) => sounds,
+        Err(e) => {
+            tracing::warn!("Skipping sample cache warm-up: {}", e);
+            return;
+        }
+    };
+    let bound: Vec<String> = database::get_hotkey_bindings()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|binding| match binding.action {
+            HotkeyAction::PlaySound { sound_id } => Some(sound_id),
+            _ => None,
+        })
+        .collect();
+    let files = sounds
+        .into_iter()
+        .filter(|sound| sound.preload || sound.hotkey.is_some() || bound.contains(&sound.id))
+        .flat_map(|sound| {
+            let variants = database::get_sound_variants(&sound.id).unwrap_or_default();
+            std::iter::once((sound.file_path, sound.preload))
+                .chain(variants.into_iter().map(move |variant| (variant.file_path, sound.preload)))
+        })
+        .collect();
+    audio::get_sample_cache().warm(files);
+}
+
+#[tauri::command]
+pub async fn get_sample_cache_config() -> Result<audio::SampleCacheConfig, String> {
+    Ok(audio::get_sample_cache().config())
+}
+
+#[tauri::command]
+pub async fn set_sample_cache_config(budget_mb: u32, max_clip_seconds: f32) -> Result<(), String> {
+    database::save_setting("sample_cache_mb", &budget_mb.to_string()).map_err(|e| e.to_string())?;
+    database::save_setting("sample_cache_max_clip_seconds", &max_clip_seconds.to_string()).map_err(|e| e.to_string())?;
+    audio::get_sample_cache().set_config(audio::SampleCacheConfig { budget_mb, max_clip_seconds });
+    Ok(())
+}
+
+// persists the table and lets playing voices pick up the new route volumes straight away
+fn apply_output_routes() -> Result<(), String> {
+    audio::save_output_routes()?;
+    audio::get_audio_engine().send_command(audio::AudioCommand::UpdateDeviceVolumes);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_output_routes() -> Result<Vec<audio::OutputRoute>, String> {
+    Ok(audio::get_audio_manager().get_routes())
+}
+
+#[tauri::command]
+pub async fn set_output_route(route: audio::OutputRoute) -> Result<(), String> {
+    audio::get_audio_manager().set_route(route).map_err(|e| e.to_string())?;
+    apply_output_routes()
+}
+
+#[tauri::command]
+pub async fn remove_output_route(name: String) -> Result<(), String> {
+    audio::get_audio_manager().remove_route(&name).map_err(|e| e.to_string())?;
+    apply_output_routes()
+}
+
+#[tauri::command]
+pub async fn set_output_route_muted(name: String, muted: bool) -> Result<(), String> {
+    audio::get_audio_manager().set_route_muted(&name, muted).map_err(|e| e.to_string())?;
+    apply_output_routes()
+}
+
+#[tauri::command]
+pub async fn set_output_route_channel_mode(name: String, channel_mode: audio::ChannelMode) -> Result<(), String> {
+    audio::get_audio_manager().set_route_channel_mode(&name, channel_mode).map_err(|e| e.to_string())?;
+    apply_output_routes()
+}
+
+#[tauri::command]
+pub async fn get_loudness_target() -> Result<Option<f32>, String> {
+    Ok(loudness_target())
+}
+
+// None turns normalization off, sounds then play at their own volume only
+#[tauri::command]
+pub async fn set_loudness_target(target: Option<f32>) -> Result<(), String> {
+    let value = target.map(|t| t.clamp(-40.0, 0.0).to_string()).unwrap_or_default();
+    database::save_setting("loudness_target", &value).map_err(|e| e.to_string())?;
+    info!("Set loudness target to {:?}", target);
+    Ok(())
+}
+
+// backfills loudness for sounds imported before analysis existed, force re-analyzes everything
+#[tauri::command]
+pub async fn analyze_library(force: Option<bool>) -> Result<usize, String> {
+    let force = force.unwrap_or(false);
+    let sounds = database::get_sounds().map_err(|e| e.to_string())?;
+    let mut analyzed = 0;
+    for sound in sounds {
+        if force || sound.integrated_loudness.is_none() {
+            if let Some(loudness) = analyze_file_loudness(sound.file_path.clone()).await {
+                database::update_sound_loudness(
+                    &sound.id,
+                    loudness.integrated_lufs,
+                    Some(loudness.true_peak_db).filter(|peak| peak.is_finite()),
+                )
+                .map_err(|e| e.to_string())?;
+                analyzed += 1;
+            }
+        }
+        // variants are normalized on their own loudness, see variant_playback
+        let variants = database::get_sound_variants(&sound.id).unwrap_or_default();
+        for variant in variants {
+            if !force && variant.integrated_loudness.is_some() {
+                continue;
+            }
+            if let Some(loudness) = analyze_file_loudness(variant.file_path.clone()).await {
+                database::update_variant_loudness(&variant.id, loudness.integrated_lufs)
+                    .map_err(|e| e.to_string())?;
+                analyzed += 1;
+            }
+        }
+    }
+    info!("Analyzed loudness of {} sounds and variants", analyzed);
+    Ok(analyzed)
+}
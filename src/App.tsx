@@ -61,7 +61,7 @@ function App() {
     handleStopSound,
     handleStopAllSounds,
     handleSeekSound,
-    getPlaybackPosition,
+    playbackPositions,
     handleVirtualVolumeChange,
     handleOutputVolumeChange,
     handleInputVolumeChange,
@@ -178,10 +178,9 @@ function App() {
   }, []);
 
   useEffect(() => {
+    // the backend already started the sound, sound-started updates the playing state
     const unlistenPlay = listen<string>('hotkey-play-sound', (event) => {
-      const soundId = event.payload;
       console.log('[frontend] Received hotkey-play-sound event:', event);
-      handlePlaySound(soundId);
     });
 
     const unlistenStop = listen('hotkey-stop-all-sounds', (event) => {
@@ -193,7 +192,7 @@ function App() {
       unlistenPlay.then(f => f());
       unlistenStop.then(f => f());
     };
-  }, [handlePlaySound, handleStopAllSounds]);
+  }, [handleStopAllSounds]);
 
 
 
@@ -530,7 +529,7 @@ function App() {
                         onSetDisplayName={handleSetDisplayName}
                         availableCategories={categories}
                         onSeek={handleSeekSound}
-                        getPlaybackPosition={getPlaybackPosition}
+                        playbackPosition={playbackPositions[sound.id]}
                         index={index}
                       />
                     ))}
@@ -557,7 +556,7 @@ function App() {
                         onSetDisplayName={handleSetDisplayName}
                         availableCategories={categories}
                         onSeek={handleSeekSound}
-                        getPlaybackPosition={getPlaybackPosition}
+                        playbackPosition={playbackPositions[sound.id]}
                       />
                     ))}
                   </div>
@@ -949,7 +948,7 @@ function App() {
             onClose={() => setIsPlayingDrawerOpen(false)}
             soundsLookup={new Map(sounds.map(s => [s.id, s]))}
             playingIds={Array.from(playingSounds)}
-            getPlaybackPosition={getPlaybackPosition}
+            playbackPositions={playbackPositions}
             onSeek={handleSeekSound}
             onStop={handleStopSound}
             onVolumeChange={handleSoundVolumeChange}
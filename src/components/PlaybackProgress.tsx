@@ -5,15 +5,10 @@ interface PlaybackProgressProps {
   currentTime?: number;
   onSeek?: (newTime: number) => void;
   disabled?: boolean;
-  isPlaying?: boolean;
-  soundId?: string;
-  getPlaybackPosition?: (soundId: string) => Promise<number | null>;
 }
 
-export const PlaybackProgress: React.FC<PlaybackProgressProps> = ({ duration, currentTime, onSeek, disabled, isPlaying, soundId, getPlaybackPosition }) => {
-  const [internalTime, setInternalTime] = React.useState(0);
-  const useInternal = !!(isPlaying && soundId && getPlaybackPosition);
-  const timeNow = useInternal ? internalTime : (currentTime || 0);
+export const PlaybackProgress: React.FC<PlaybackProgressProps> = ({ duration, currentTime, onSeek, disabled }) => {
+  const timeNow = currentTime || 0;
   const progress = duration > 0 ? (timeNow / duration) * 100 : 0;
 
   const handleClick = (e: React.MouseEvent<HTMLDivElement, MouseEvent>) => {
@@ -25,21 +20,6 @@ export const PlaybackProgress: React.FC<PlaybackProgressProps> = ({ duration, cu
     onSeek(newTime);
   };
 
-  React.useEffect(() => {
-    if (!useInternal) return;
-    let mounted = true;
-    const tick = async () => {
-      if (!mounted || !getPlaybackPosition || !soundId) return;
-      try {
-        const pos = await getPlaybackPosition(soundId);
-        if (pos !== null) setInternalTime(pos);
-      } catch {}
-    };
-    const interval = setInterval(tick, 100);
-    tick();
-    return () => { mounted = false; clearInterval(interval); };
-  }, [useInternal, getPlaybackPosition, soundId]);
-
   const formatTime = (seconds: number): string => {
     const mins = Math.floor(seconds / 60);
     const secs = Math.floor(seconds % 60);
@@ -1,4 +1,4 @@
-import React, { useEffect, useState } from 'react';
+import React, { useEffect } from 'react';
 import { X, Square } from 'lucide-react';
 import { Sound } from '../types';
 import { PlaybackProgress } from './PlaybackProgress';
@@ -9,35 +9,15 @@ interface PlayingDrawerProps {
   onClose: () => void;
   soundsLookup: Map<string, Sound>;
   playingIds: string[];
-  getPlaybackPosition?: (soundId: string) => Promise<number | null>;
+  // sound id -> seconds in, pushed by the audio thread's progress events
+  playbackPositions: Record<string, number>;
   onSeek?: (soundId: string, position: number) => void;
   onStop?: (soundId: string) => void;
   onVolumeChange?: (soundId: string, volume: number) => void;
   onStopAll?: () => void;
 }
 
-export const PlayingDrawer: React.FC<PlayingDrawerProps> = ({ open, onClose, soundsLookup, playingIds, getPlaybackPosition, onSeek, onStop, onVolumeChange, onStopAll }) => {
-  const [positions, setPositions] = useState<Record<string, number>>({});
-
-  useEffect(() => {
-    if (!open || !getPlaybackPosition || playingIds.length === 0) return;
-    let mounted = true;
-    const tick = async () => {
-      if (!mounted) return;
-      const updates: Record<string, number> = {};
-      for (const id of playingIds) {
-        try {
-          const pos = await getPlaybackPosition(id);
-          if (pos !== null) updates[id] = pos;
-        } catch {}
-      }
-      if (mounted) setPositions(prev => ({ ...prev, ...updates }));
-    };
-    const interval = setInterval(tick, 100);
-    tick();
-    return () => { mounted = false; clearInterval(interval); };
-  }, [open, playingIds.join('|'), getPlaybackPosition]);
-
+export const PlayingDrawer: React.FC<PlayingDrawerProps> = ({ open, onClose, soundsLookup, playingIds, playbackPositions, onSeek, onStop, onVolumeChange, onStopAll }) => {
   useEffect(() => {
     if (!open) return;
     const handler = (e: KeyboardEvent) => {
@@ -75,7 +55,7 @@ export const PlayingDrawer: React.FC<PlayingDrawerProps> = ({ open, onClose, sou
           const sound = soundsLookup.get(id);
           if (!sound) return null;
           const duration = sound.duration || 0;
-          const currentTime = positions[id] ?? 0;
+          const currentTime = playbackPositions[id] ?? 0;
           return (
             <div key={id} className="bg-gray-900/40 border border-gray-800 rounded-lg p-3">
               <div className="flex items-center justify-between mb-2 gap-2">
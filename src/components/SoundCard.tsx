@@ -1,4 +1,3 @@
-import { useState, useEffect } from 'react';
 import { Play, Square, Headphones, Clock } from 'lucide-react';
 import { PlaybackProgress } from './PlaybackProgress';
 import { WaveformAnimation } from './WaveformAnimation';
@@ -21,7 +20,8 @@ interface SoundCardProps {
   onSetDisplayName: (soundId: string, displayName: string | null) => void;
   availableCategories: string[];
   onSeek?: (soundId: string, position: number) => void;
-  getPlaybackPosition?: (soundId: string) => Promise<number | null>;
+  // seconds into the sound, pushed by the audio thread's progress events
+  playbackPosition?: number;
   index: number;
 }
 
@@ -57,21 +57,15 @@ export const SoundCard = ({
   onSetDisplayName,
   availableCategories,
   onSeek,
-  getPlaybackPosition,
+  playbackPosition,
   index 
 }: SoundCardProps) => {
-  const [currentTime, setCurrentTime] = useState(0);
-
-  useEffect(() => {
-    if (!isPlaying) setCurrentTime(0);
-  }, [isPlaying]);
-
+  const currentTime = isPlaying ? playbackPosition ?? 0 : 0;
   const duration = sound.duration || 0;
 
   const handleSeek = (newTime: number) => {
     if (!isPlaying || !duration || !onSeek) return;
     onSeek(sound.id, newTime);
-    setCurrentTime(newTime);
   };
 
   return (
@@ -136,9 +130,6 @@ export const SoundCard = ({
             duration={duration} 
             currentTime={currentTime} 
             onSeek={handleSeek}
-            isPlaying={isPlaying}
-            soundId={sound.id}
-            getPlaybackPosition={getPlaybackPosition}
           />
         </div>
       )}
@@ -19,7 +19,8 @@ interface SoundListItemProps {
   onSetDisplayName: (soundId: string, displayName: string | null) => void;
   availableCategories: string[];
   onSeek?: (soundId: string, position: number) => void;
-  getPlaybackPosition?: (soundId: string) => Promise<number | null>;
+  // seconds into the sound, pushed by the audio thread's progress events
+  playbackPosition?: number;
 }
 
 const formatTime = (seconds: number): string => {
@@ -54,7 +55,7 @@ export const SoundListItem = ({
   onSetDisplayName,
   availableCategories,
   onSeek,
-  getPlaybackPosition,
+  playbackPosition,
 }: SoundListItemProps) => {
   const duration = sound.duration || 0;
   const displayName = sound.display_name || sound.name;
@@ -147,13 +148,11 @@ export const SoundListItem = ({
 
       {isPlaying && duration > 0 && (
         <div className="mt-3">
-                     <PlaybackProgress
-             soundId={sound.id}
-             duration={duration}
-             onSeek={onSeek ? (newTime: number) => onSeek(sound.id, newTime) : undefined}
-             getPlaybackPosition={getPlaybackPosition}
-             isPlaying={isPlaying}
-           />
+          <PlaybackProgress
+            duration={duration}
+            currentTime={playbackPosition ?? 0}
+            onSeek={onSeek ? (newTime: number) => onSeek(sound.id, newTime) : undefined}
+          />
         </div>
       )}
     </div>
@@ -1,6 +1,7 @@
-import { useState, useEffect } from 'react';
+import { useState, useEffect, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/core';
-import { AudioDevice } from '../types';
+import { listen } from '@tauri-apps/api/event';
+import { AudioDevice, DevicesChangedEvent, MissingDevice, PlayingSoundInfo, SelectedDevices, SoundErrorEvent } from '../types';
 
 export const useAudio = (showAllOutputDevices: boolean = false) => {
   const [audioDevices, setAudioDevices] = useState<AudioDevice[]>([]);
@@ -13,12 +14,37 @@ export const useAudio = (showAllOutputDevices: boolean = false) => {
   const [isInputCapturing, setIsInputCapturing] = useState(false);
   const [playingSounds, setPlayingSounds] = useState<Set<string>>(new Set());
   const [localOnlySounds, setLocalOnlySounds] = useState<Set<string>>(new Set());
+  const [missingDevices, setMissingDevices] = useState<MissingDevice[]>([]);
+  // voice id -> latest info pushed by the audio thread
+  const voicesRef = useRef<Map<string, PlayingSoundInfo>>(new Map());
+  // sound id -> position of its earliest voice, re-rendered on every push from the audio thread
+  const [playbackPositions, setPlaybackPositions] = useState<Record<string, number>>({});
+
+  const publishPositions = () => {
+    const positions: Record<string, number> = {};
+    for (const voice of voicesRef.current.values()) {
+      const known = positions[voice.sound_id];
+      positions[voice.sound_id] = known === undefined ? voice.position : Math.min(known, voice.position);
+    }
+    setPlaybackPositions(positions);
+  };
 
   const loadAudioDevices = async () => {
     try {
-      const devices = await invoke<AudioDevice[]>('get_audio_devices');
+      const [devices, selected, missing] = await Promise.all([
+        invoke<AudioDevice[]>('get_audio_devices'),
+        invoke<SelectedDevices>('get_selected_devices'),
+        invoke<MissingDevice[]>('get_missing_devices'),
+      ]);
       
             setAudioDevices(devices);
+      setMissingDevices(missing);
+
+      // the backend restores the saved devices, the guesses below only cover a first launch
+      if (selected.virtual_device) setSelectedVirtualDevice(selected.virtual_device);
+      if (selected.output_device) setSelectedOutputDevice(selected.output_device);
+      if (selected.input_device) setSelectedInputDevice(selected.input_device);
+      if (selected.virtual_device && selected.output_device && selected.input_device) return;
       
       const vbCableDevice = devices.find(d => 
         d.device_type === 'virtual' && (
@@ -27,17 +53,17 @@ export const useAudio = (showAllOutputDevices: boolean = false) => {
           d.name.toLowerCase().includes('virtual')
         )
       );
-      if (vbCableDevice) {
+      if (vbCableDevice && !selected.virtual_device) {
         setSelectedVirtualDevice(vbCableDevice.name);
       }
       
       const defaultOutputDevice = devices.find(d => d.device_type === 'output' && d.is_default);
-      if (defaultOutputDevice) {
+      if (defaultOutputDevice && !selected.output_device) {
         setSelectedOutputDevice(defaultOutputDevice.name);
       }
 
       const defaultInputDevice = devices.find(d => d.device_type === 'input' && d.is_default);
-      if (defaultInputDevice) {
+      if (defaultInputDevice && !selected.input_device) {
         setSelectedInputDevice(defaultInputDevice.name);
       }
     } catch (error) {
@@ -47,12 +73,14 @@ export const useAudio = (showAllOutputDevices: boolean = false) => {
 
   const loadVolume = async () => {
     try {
-      const [virtualVol, outputVol] = await Promise.all([
+      const [virtualVol, outputVol, inputVol] = await Promise.all([
         invoke<number>('get_virtual_volume'),
         invoke<number>('get_output_volume'),
+        invoke<number>('get_input_volume'),
       ]);
       setVirtualVolume(virtualVol);
       setOutputVolume(outputVol);
+      setInputVolume(inputVol);
     } catch (error) {
       console.error('Failed to load volume:', error);
     }
@@ -143,6 +171,10 @@ export const useAudio = (showAllOutputDevices: boolean = false) => {
   };
 
   const getPlaybackPosition = async (soundId: string): Promise<number | null> => {
+    const latest = Array.from(voicesRef.current.values())
+      .filter(v => v.sound_id === soundId)
+      .sort((a, b) => a.position - b.position)[0];
+    if (latest) return latest.position;
     try {
       const position = await invoke<number | null>('get_playback_position', { soundId });
       return position;
@@ -179,6 +211,7 @@ export const useAudio = (showAllOutputDevices: boolean = false) => {
     try {
       await invoke('set_virtual_device', { deviceName });
       setSelectedVirtualDevice(deviceName);
+      setMissingDevices(prev => prev.filter(m => m.bus !== 'virtual'));
     } catch (error) {
       console.error('Failed to set virtual device:', error);
     }
@@ -188,6 +221,7 @@ export const useAudio = (showAllOutputDevices: boolean = false) => {
     try {
       await invoke('set_output_device', { deviceName });
       setSelectedOutputDevice(deviceName);
+      setMissingDevices(prev => prev.filter(m => m.bus !== 'output'));
     } catch (error) {
       console.error('Failed to set output device:', error);
     }
@@ -197,6 +231,7 @@ export const useAudio = (showAllOutputDevices: boolean = false) => {
     try {
       await invoke('set_input_device', { deviceName });
       setSelectedInputDevice(deviceName);
+      setMissingDevices(prev => prev.filter(m => m.bus !== 'input'));
       if (isInputCapturing) {
         try { await invoke('start_input_capture'); } catch (e) { console.error('Failed to restart input capture:', e); }
       }
@@ -253,36 +288,55 @@ export const useAudio = (showAllOutputDevices: boolean = false) => {
 
 
   useEffect(() => {
-    if (playingSounds.size === 0) return;
-
-    const interval = setInterval(async () => {
-      try {
-
-        const currentlyPlaying = await invoke<string[]>('get_playing_sounds');
-        console.log('Currently playing sounds:', currentlyPlaying);
-        
-
-        setPlayingSounds(() => {
-          const newSet = new Set(currentlyPlaying);
-          return newSet;
-        });
-        
-        setLocalOnlySounds(prev => {
-          const newLocalOnlySet = new Set<string>();
-          for (const soundId of currentlyPlaying) {
-            if (prev.has(soundId)) {
-              newLocalOnlySet.add(soundId);
-            }
-          }
-          return newLocalOnlySet;
-        });
-      } catch (error) {
-        console.error('Failed to check playing sounds:', error);
-      }
-    }, 500);
-
-    return () => clearInterval(interval);
-  }, [playingSounds]);
+    const voiceEnded = (event: { payload: PlayingSoundInfo }) => {
+      const { sound_id, voice_id } = event.payload;
+      voicesRef.current.delete(voice_id);
+      publishPositions();
+      const stillPlaying = Array.from(voicesRef.current.values()).some(v => v.sound_id === sound_id);
+      if (stillPlaying) return;
+      setPlayingSounds(prev => {
+        const newSet = new Set(prev);
+        newSet.delete(sound_id);
+        return newSet;
+      });
+      setLocalOnlySounds(prev => {
+        const newSet = new Set(prev);
+        newSet.delete(sound_id);
+        return newSet;
+      });
+    };
+
+    const unlisteners = Promise.all([
+      listen<PlayingSoundInfo>('sound-started', (event) => {
+        voicesRef.current.set(event.payload.voice_id, event.payload);
+        publishPositions();
+        setPlayingSounds(prev => new Set(prev).add(event.payload.sound_id));
+      }),
+      listen<PlayingSoundInfo>('sound-finished', voiceEnded),
+      listen<PlayingSoundInfo>('sound-stopped', voiceEnded),
+      listen<PlayingSoundInfo[]>('sound-progress', (event) => {
+        for (const info of event.payload) {
+          voicesRef.current.set(info.voice_id, info);
+        }
+        publishPositions();
+      }),
+      listen<SoundErrorEvent>('sound-error', (event) => {
+        console.error('Sound failed to play:', event.payload);
+      }),
+      listen<DevicesChangedEvent>('audio-devices-changed', (event) => {
+        console.log('Audio devices changed:', event.payload);
+        loadAudioDevices();
+      }),
+      listen<MissingDevice>('audio-device-missing', (event) => {
+        console.warn('Saved audio device is missing:', event.payload);
+        setMissingDevices(prev => [...prev.filter(m => m.bus !== event.payload.bus), event.payload]);
+      }),
+    ]);
+
+    return () => {
+      unlisteners.then(fns => fns.forEach(unlisten => unlisten()));
+    };
+  }, []);
 
   useEffect(() => {
     loadAudioDevices();
@@ -360,11 +414,13 @@ export const useAudio = (showAllOutputDevices: boolean = false) => {
     isInputCapturing,
     playingSounds,
     localOnlySounds,
+    missingDevices,
     handlePlaySound,
     handleStopSound,
     handleStopAllSounds,
     handleSeekSound,
     getPlaybackPosition,
+    playbackPositions,
     handleVirtualVolumeChange,
     debugPlaybackPosition,
     handleOutputVolumeChange,
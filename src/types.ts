@@ -10,6 +10,30 @@ export interface Hotkey {
   modifiers: Modifiers;
 }
 
+export type PlayMode = 'restart' | 'overlap' | 'toggle' | 'ignore_while_playing' | 'hold';
+
+export type LoopMode = 'off' | 'file' | 'region';
+
+export type Effect =
+  | { type: 'speed'; rate: number; preserve_pitch?: boolean }
+  | { type: 'pitch_shift'; semitones: number }
+  | { type: 'reverb'; room_size: number; damping: number; mix: number }
+  | { type: 'echo'; delay_ms: number; feedback: number; mix: number }
+  | { type: 'equalizer'; low_db: number; mid_db: number; high_db: number }
+  | { type: 'low_pass'; cutoff_hz: number }
+  | { type: 'high_pass'; cutoff_hz: number }
+  | { type: 'bitcrush'; bits: number; downsample: number }
+  | { type: 'distortion'; drive: number; mix: number };
+
+export interface SoundVariant {
+  id: string;
+  sound_id: string;
+  file_path: string;
+  weight: number;
+  integrated_loudness?: number | null;
+  created_at: string;
+}
+
 export interface Sound {
   id: string;
   name: string;
@@ -21,6 +45,27 @@ export interface Sound {
   volume: number;
   startPosition?: number;
   duration?: number;
+  play_mode?: PlayMode;
+  loop_mode?: LoopMode;
+  loop_start?: number;
+  loop_end?: number;
+  loop_count?: number;
+  end_position?: number;
+  fade_in_ms?: number;
+  fade_out_ms?: number;
+  choke_group?: string;
+  priority?: number;
+  integrated_loudness?: number;
+  true_peak?: number;
+  output_routes?: string[] | null;
+  preload?: boolean;
+  effects?: Effect[];
+  pan?: number;
+  no_repeat?: boolean;
+  pitch_jitter?: number;
+  volume_jitter_db?: number;
+  own_weight?: number;
+  variants?: SoundVariant[];
   created_at: string;
   updated_at: string;
 }
@@ -31,6 +76,106 @@ export interface AudioDevice {
   device_type: string;
 }
 
+export type ChannelMode = 'stereo' | 'mono' | 'swap';
+
+export interface OutputRoute {
+  name: string;
+  device?: string | null;
+  volume: number;
+  muted: boolean;
+  channel_mode?: ChannelMode;
+}
+
+export interface NoiseGateConfig {
+  enabled: boolean;
+  threshold_db: number;
+  attack_ms: number;
+  release_ms: number;
+}
+
+export interface AutoGainConfig {
+  enabled: boolean;
+  target_db: number;
+  max_gain_db: number;
+}
+
+export interface CompressorConfig {
+  enabled: boolean;
+  threshold_db: number;
+  ratio: number;
+  attack_ms: number;
+  release_ms: number;
+  makeup_db: number;
+}
+
+export interface MicProcessingConfig {
+  noise_gate: NoiseGateConfig;
+  auto_gain: AutoGainConfig;
+  compressor: CompressorConfig;
+}
+
+export interface DuckingConfig {
+  enabled: boolean;
+  amount_db: number;
+  attack_ms: number;
+  release_ms: number;
+  duck_mic: boolean;
+  music_category?: string | null;
+}
+
+export interface SampleCacheConfig {
+  budget_mb: number;
+  max_clip_seconds: number;
+}
+
+export interface SampleCacheStats {
+  entries: number;
+  used_bytes: number;
+  budget_bytes: number;
+  hits: number;
+  misses: number;
+  evictions: number;
+}
+
+export interface SelectedDevices {
+  virtual_device?: string | null;
+  output_device?: string | null;
+  input_device?: string | null;
+}
+
+export interface DevicesChangedEvent {
+  added: string[];
+  removed: string[];
+  rebound: string[];
+}
+
+export interface MissingDevice {
+  bus: string;
+  device: string;
+  fallback?: string | null;
+}
+
+export interface DevicePosition {
+  device: string;
+  position: number;
+}
+
+export interface PlayingSoundInfo {
+  sound_id: string;
+  voice_id: string;
+  position: number;
+  duration?: number | null;
+  paused: boolean;
+  priority: number;
+  devices: DevicePosition[];
+}
+
+export interface SoundErrorEvent {
+  sound_id: string;
+  device?: string | null;
+  error: string;
+}
+
 export type TabType = 'sounds' | 'youtube' | 'settings';
 export type ViewType = 'grid' | 'list';
 
@@ -61,11 +206,21 @@ export interface DownloadProgress {
 export interface HotkeyBinding {
   id: string;
   hotkey: string;
-  action: 'PlaySound' | 'StopAllSounds';
+  action: 'PlaySound' | 'StopAllSounds' | MicHotkeyAction;
   soundId?: string;
   createdAt: string;
 }
 
+export type MicHotkeyAction = 'PushToTalk' | 'PushToMute' | 'ToggleMic';
+
+export interface MicState {
+  push_to_talk: boolean;
+  talking: boolean;
+  push_muted: boolean;
+  toggled_off: boolean;
+  open: boolean;
+}
+
 export interface GlobalHotkey {
   key: string;
   value: string;